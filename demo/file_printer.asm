@@ -1,10 +1,52 @@
 
 %header version 0
 
-%entry main
+%entry start
 
 %section code
 
+# erebos_vm file_printer.bin -- a.txt b.txt prints the files and stops
+# without arguments it asks for paths forever
+start:
+    cmprr ra, rz
+    jifi main, E # ra is 0 without an argument block
+
+    movir 4, rb
+    addrrr ra, rb, rb
+    movrar rb, r1 # r1 = argv
+    movrar ra, r2 # r2 = argc
+    movir 1, r3   # argv[0] is this program
+ _args_loop:
+    cmprr r3, r2
+    jifi _args_end, EA
+
+    movir 4, rb
+    mulrrr r3, rb, rb
+    addrrr r1, rb, rb
+    movrar rb, ra # ra = argv[r3]
+    movir buffer, rb
+    cali strcpy
+
+    movir buffer, ra
+    __io 0x04 # FileExists()
+    cmprr rz, rb
+    jifi _args_invalid, E
+
+    cali readfile
+    jmpi _args_next
+ _args_invalid:
+    movir error_invalid0, ra
+    cali putstr
+    movir buffer, ra
+    cali putstr
+    movir error_invalid1, ra
+    cali putstr
+ _args_next:
+    inc r3
+    jmpi _args_loop
+ _args_end:
+    hlt
+
 main:
     movir shell_pre, ra
     cali putstr
@@ -104,6 +146,19 @@ putstr_len: # ra=ptr, rb=len
  _putrstr_end:
     ret
 
+strcpy: # ra=src, rb=dst [with the 0]
+    movir 1, rd
+ _strcpy_loop:
+    bmovrar ra, rc
+    bmovrra rc, rb
+    cmprr rc, rz
+    jifi _strcpy_end, E
+    addrrr ra, rd, ra
+    addrrr rb, rd, rb
+    jmpi _strcpy_loop
+ _strcpy_end:
+    ret
+
 strlen: # ra=ptr
     movrr ra, rb
     movir 1, rc
@@ -130,7 +185,7 @@ readfile:
     cmprr ra, rc
     jifi _readfile_end, EA # counter >= length, break
 
-    movrar ra, rb
+    bmovrar ra, rb
     __out rb # get char and print
     inc ra
     jmpi _readfile_loop
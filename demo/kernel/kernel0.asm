@@ -11,6 +11,7 @@
 %ext shell_try_run_cmd
 %ext shell_info_running
 %ext terminate_running_program
+%ext info_shell_rsp
 
 MAIN:
 
@@ -19,6 +20,11 @@ MAIN:
     __io 0xF0
     movir INT, ra
     __io 0x02
+    movir 1, ra
+    __io 0x08 # SetKeyboardInterrupt(), lets readline sleep in wfi
+    movir 0x1400, ra
+    movir 0x400, rb
+    __io 0x09 # SetKernelStack(), right after the main stack
 
     movim 1, shell_info_running
     # activate shell
@@ -43,9 +49,13 @@ _INT_get_adr:
     __io 0xF0 # iodevice = InterruptHandler
     ret
 
-INT:
-    movrm r1, TMP_REG
+INT: # syscall lands here on the kernel stack, int on whatever stack was active
     pshr ra
+    pshr rb
+    pshr rc
+    pshr rd
+    pshr r1 # sysret keeps registers, so everything touched here gets saved
+    movrr ra, rd # syscall id
     
     movir 2, ra
     __io 0xF0 # iodevice = MemoryManager
@@ -58,8 +68,8 @@ INT:
     movir 2, r1
 
     cmprr ra, r1
-    popr ra
     jifi _INT_default, AB # skip if not syscall
+    movrr rd, ra
 
     cmprr ra, rz
  jifi _INT_sys_not_00, AB
@@ -79,14 +89,19 @@ INT:
         cali terminate_running_program
         movir '\n, ra
         __out ra
-        popr r1
-        jmpi MAIN_LOOP
+        __io 0x04 # RemoveInterrupt(), back on the stack the program ran on
+        movmr info_shell_rsp, rsp # drop whatever the program left there
+        ret # from execute_loaded_file, the exec command finishes as usual
  _INT_sys_not_01:
 
     movir 3, r1
     cmprr ra, r1
  jifi _INT_sys_not_03, AB # in
         __in  rb
+        movrr rsp, rc
+        movir 16, rd
+        subrrr rc, rd, rc
+        movrra rb, rc # overwrite the saved rb, it is the return value
  _INT_sys_not_03:
 
     movir 4, r1
@@ -101,32 +116,55 @@ INT:
     cmprr ra, r1
  jifi _INT_sys_not_05, AB # getstr
         cali _INT_get_adr
-        movrr rb, ra
-        cali readline
+        pshr rb # buffer of the program
+        movir syscall_line_buffer, ra
+        cali readline # length with the 0 in rb
+        movrr rb, rc
+        popr ra
+        movir syscall_line_buffer, rb
+        memcpy ra, rb, rc # hand the line over in one go
  _INT_sys_not_05:
 
     movir 6, r1
     cmprr ra, r1
- jifi _INT_sys_not_06, AB # ray call
+ jifi _INT_sys_not_06, AB # ray call, the arguments sit on the stack of the caller
     movir 2, ra
     __io 0xF0 # iodevice = MemoryManager
     __io 0x01 # ResumeMapping()
+    popr r1
+    popr rd
+    popr rc
+    popr rb
+    popr ra
         movrr rb, ra
         __io 0xF1
-    __io 0x00 # SuspendMapping()
     
     movir 1, ra
     __io 0xF0 # iodevice = InterruptHandler
+    sysret
  _INT_sys_not_06:
 
-    movmr TMP_REG, r1
-    jmpi _INT_sysret
+    movir 2, ra
+    __io 0xF0 # iodevice = MemoryManager
+    __io 0x01 # ResumeMapping()
+    
+    movir 1, ra
+    __io 0xF0 # select int handler for io
+
+    popr r1
+    popr rd
+    popr rc
+    popr rb
+    popr ra
+    sysret
 
  _INT_default:
-    movmr TMP_REG, r1
+    movir 4, r1
+    cmprr ra, r1
+    jifi _INT_resolve, E # keyboard, the key is picked up by __in after wfi
     movir msg1, ra
     cali putstr    
- _INT_sysret:
+ _INT_resolve:
 
     movir 2, ra
     __io 0xF0 # iodevice = MemoryManager
@@ -135,7 +173,7 @@ INT:
     movir 1, ra
     __io 0xF0 # select int handler for io
 
-    __io 0x06 # ResolveInterruptNoRSP()
+    __io 0x03 # ResolveInterrupt(), restores every register and rsp
     dbg # unreachable
 hlt
 
@@ -195,8 +233,6 @@ SHELL_DBG:
 
 %section data
 
-TMP_REG: dd 0
-
 msg0: db "hewwwooo mrrrrrp :3\n",0
 msg1: db "INTERRUPT\n",0
 
@@ -206,3 +242,5 @@ txt2_0: db "The command '",0
 txt2_1: db "' doesnt exist!\n",0
 txt3: db "Invalid 0 syscall!\n",0
 
+syscall_line_buffer: resb 500
+
@@ -53,6 +53,7 @@ readline: # ra=ptr, reads chars till \n, puts them into buffer, also adds 0 at t
           # puts read length into rb [including 0 char]
     movrr ra, rb # copy ptr into counter
  _readline_loop:
+    wfi # sleep till a key is there
     __in rc
     cmprm rc, char_backspace
     jifi _readline_back, E # handle backspace
@@ -90,6 +91,8 @@ readline: # ra=ptr, reads chars till \n, puts them into buffer, also adds 0 at t
 %ext __FILE_length
 %exp execute_loaded_file
 execute_loaded_file:
+    movrm rsp, info_shell_rsp # exit comes back here, returning to the caller
+
     movir 2, ra
     __io 0xF0 # switch io to MemoryManager
 
@@ -122,8 +125,6 @@ terminate_running_program:
     movir 1, ra
     __io 0xF0 # switch io to InterruptHandler
     
-    __io 0x4 # RemoveInterrupt()
-    
     ret
 
 hlt
@@ -133,6 +134,9 @@ hlt
 %exp info_execution_mem_map_id
 info_execution_mem_map_id: db 0,0,0,0
 
+%exp info_shell_rsp
+info_shell_rsp: db 0,0,0,0
+
 %exp char_newline
 %exp char_newline0
 char_newline: db 0,0,0
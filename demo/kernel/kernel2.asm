@@ -38,6 +38,10 @@ shell_readline:
 %ext shell_input_vector
 %exp shell_split_args
 shell_split_args:
+    movir shell_input_vector, ra
+    movir 100, rc
+    memset ra, rz, rc # clear entries of the last command
+
     movir 0, rb # non zero means should put entry on not space
     movir shell_input_buffer, rc
     movir shell_input_vector, rd
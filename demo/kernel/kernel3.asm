@@ -6,6 +6,7 @@
 %ext shell_input_vector
 %ext __FILE
 %ext __FILE_length
+%ext __FILE_end
 %ext execute_loaded_file
 
 _cmd_help_fun:
@@ -78,6 +79,13 @@ _cmd_exec_fun:
     cmprr rb, rz
     jifi _cmd_exec_fun_err0, E # if file doesnt exist err
     
+    pshr ra
+    movir __FILE, rb
+    movir __FILE_end, rc
+    subrrr rc, rb, rc # header and program space
+    memset rb, rz, rc # wipe whatever the last program left behind
+    popr ra
+
     movir __FILE, rb
     __io 0x0E # QuickRead()
 
@@ -86,9 +94,7 @@ _cmd_exec_fun:
 
     movrm rc, __FILE_length
 
-    cali execute_loaded_file
- # unreachable
-    dbg
+    cali execute_loaded_file # returns once the program exits
     ret
 
  _cmd_exec_fun_err0:
@@ -4,6 +4,8 @@
 %exp __FILE
 %exp __FILE_entry
 %exp __FILE_length
+%exp __FILE_end
 __FILE_length: resb 2
 __FILE: resb 32
 __FILE_entry: resb 500
+__FILE_end:
@@ -3,18 +3,18 @@
 
 movir 0x04, ra
 movir msg, rb
-int 0x02 # putstr
+syscall # putstr
 
 movir 0x05, ra
 movir buffer, rb
-int 0x02 # getstr
+syscall # getstr
 
 movir 0x04, ra
 movir buffer, rb
-int 0x02 # putstr
+syscall # putstr
 
 movir 0x01, ra
-int 0x02 # exit
+syscall # exit
 
 %section data
 
@@ -222,14 +222,22 @@ impl AsmTokenizer
                     Token::String(str, loc)
 
                 }
-                else if(c.0.is_numeric())
+                else if(c.0.is_numeric() || (c.0 == '-' && self.source[self.position..].starts_with(|n: char| n.is_ascii_digit())))
                 {
 
                     let mut base = 10;
 
                     let mut str = String::new();
+
+                    // a minus right in front of a digit makes a negative number [-5, -0x10, -2.5], anywhere else it is part of an identifier
+                    let first = if(c.0 == '-')
+                    {
+                        str.push(self.next()?.0);
+                        *self.peek()?.0
+                    }
+                    else { c.0 };
                     
-                    if(c.0 == '0')
+                    if(first == '0')
                     {
                         self.next()?;
                         let n = self.peek()?;
@@ -241,7 +249,7 @@ impl AsmTokenizer
                             'b' => { base =  2; self.next()?; },
                             _ =>
                             {
-                                str.push(c.0);
+                                str.push(first);
                                 // if(!n.0.is_numeric())
                                 // {
                                 //     return Some(Err(error_in!((n.1), "Invalid token '{}' in number!", n.0)));
@@ -268,6 +276,26 @@ impl AsmTokenizer
                         str.push(self.next()?.0);
                     }
 
+                    if(base == 10 && matches!(self.peek(), Some((&'.', _))))
+                    {
+
+                        // float literal, stored as the raw bits of an f32
+                        str.push(self.next()?.0);
+
+                        while let Some(c) = self.peek()
+                        {
+                            if(!c.0.is_numeric()) { break; }
+                            str.push(self.next()?.0);
+                        }
+
+                        return Some(match str.parse::<f32>()
+                        {
+                            Ok(f) => Ok(Token::Number(f.to_bits() as i64, loc)),
+                            Err(_) => Err(error!("{}: Could not parse float '{}'!", loc, str)),
+                        });
+
+                    }
+
                     let num = match i64::from_str_radix(&str, base)
                     {
                         Ok(n) => n,
@@ -885,6 +913,8 @@ pub mod __asm
         director: AsmDirector,
         token: Option<Token>,
         requested_labels: Vec<LabelRequest>,
+        /// (request index, instruction start, instruction end, instruction) of requests that can be encoded pc relative
+        relative_requests: Vec<(usize, usize, usize, IRInstruction)>,
 
     }
     impl ASM
@@ -901,6 +931,7 @@ pub mod __asm
                 director: AsmDirector::code(src),
                 token: None,
                 requested_labels: Vec::new(),
+                relative_requests: Vec::new(),
 
             }
         }
@@ -912,6 +943,7 @@ pub mod __asm
                 director: AsmDirector::file(file)?,
                 token: None,
                 requested_labels: Vec::new(),
+                relative_requests: Vec::new(),
 
             })
         }
@@ -1032,7 +1064,7 @@ pub mod __asm
         fn register_label_request(&mut self, name: String, loc: SourceLocation, pos: u32)
         {
             self.requested_labels.push(LabelRequest {
-                name, loc, pos
+                name, loc, pos, rel: None
             });
         }
 
@@ -1106,6 +1138,9 @@ pub mod __asm
             {
                 "nop"    => return Ok((IRInstruction::NOP   , debug)),
                 "hlt"    => return Ok((IRInstruction::HLT   , debug)),
+                "wfi"    => return Ok((IRInstruction::WFI   , debug)),
+                "syscall"=> return Ok((IRInstruction::SYSCALL, debug)),
+                "sysret" => return Ok((IRInstruction::SYSRET, debug)),
                 "clf"    => return Ok((IRInstruction::CLF   , debug)),
                 "pshflg" => return Ok((IRInstruction::PSHFLG, debug)),
                 "popflg" => return Ok((IRInstruction::POPFLG, debug)),
@@ -1121,6 +1156,16 @@ pub mod __asm
                     let reg = get_reg(0, "lea")?;
                     return Ok((IRInstruction::LEA(reg), debug));
                 },
+                "leap" =>
+                {
+                    if(exp.args.len() != 2)
+                    {
+                        return Err(err_expect_args("leap", 2));
+                    }
+                    let imm = get_imm(0, 0, "leap")?;
+                    let reg = get_reg(1, "leap")?;
+                    return Ok((IRInstruction::LEAREL(imm, reg), debug));
+                },
 
                 "inc" =>
                 {
@@ -1141,6 +1186,76 @@ pub mod __asm
                     return Ok((IRInstruction::DEC(reg), debug));
                 },
 
+                "itof" =>
+                {
+                    if(exp.args.is_empty())
+                    {
+                        return Err(err_expect_args("itof", 1));
+                    }
+                    let reg = get_reg(0, "itof")?;
+                    return Ok((IRInstruction::ITOF(reg), debug));
+                },
+                "ftoi" =>
+                {
+                    if(exp.args.is_empty())
+                    {
+                        return Err(err_expect_args("ftoi", 1));
+                    }
+                    let reg = get_reg(0, "ftoi")?;
+                    return Ok((IRInstruction::FTOI(reg), debug));
+                },
+                "fsqrt" =>
+                {
+                    if(exp.args.is_empty())
+                    {
+                        return Err(err_expect_args("fsqrt", 1));
+                    }
+                    let reg = get_reg(0, "fsqrt")?;
+                    return Ok((IRInstruction::FSQRT(reg), debug));
+                },
+
+                "memcpy" | "memset" =>
+                {
+                    if(exp.args.len() != 3)
+                    {
+                        return Err(err_expect_args(&name, 3));
+                    }
+                    let r0 = get_reg(0, &name)?;
+                    let r1 = get_reg(1, &name)?;
+                    let r2 = get_reg(2, &name)?;
+                    if(name == "memcpy")
+                    {
+                        return Ok((IRInstruction::MEMCPY(r0, r1, r2), debug));
+                    }
+                    return Ok((IRInstruction::MEMSET(r0, r1, r2), debug));
+                },
+
+                "xchg" | "xadd" =>
+                {
+                    if(exp.args.len() != 2)
+                    {
+                        return Err(err_expect_args(&name, 2));
+                    }
+                    let r0 = get_reg(0, &name)?;
+                    let r1 = get_reg(1, &name)?;
+                    if(name == "xchg")
+                    {
+                        return Ok((IRInstruction::XCHG(r0, r1), debug));
+                    }
+                    return Ok((IRInstruction::XADD(r0, r1), debug));
+                },
+                "cas" =>
+                {
+                    if(exp.args.len() != 3)
+                    {
+                        return Err(err_expect_args("cas", 3));
+                    }
+                    let r0 = get_reg(0, "cas")?;
+                    let r1 = get_reg(1, "cas")?;
+                    let r2 = get_reg(2, "cas")?;
+                    return Ok((IRInstruction::CAS(r0, r1, r2), debug));
+                },
+
                 "__out" =>
                 {
                     if(exp.args.is_empty())
@@ -1329,6 +1444,7 @@ pub mod __asm
                         "r" => IRInstructionModifier::Register (get_reg(0,    "jmpr")?),
                         "m" => IRInstructionModifier::Memory   (get_imm(0, 0, "jmpm")?),
                         "i" => IRInstructionModifier::Immediate(get_imm(0, 0, "jmpi")?),
+                        "p" => return Ok((IRInstruction::JMPREL(get_imm(0, 0, "jmpp")?), debug)),
                          _  => return Err(err_unknown()),
                     };
 
@@ -1344,12 +1460,14 @@ pub mod __asm
                     }
                     
                     let mut _inner_offset = 0;
+                    let mut relative = false;
 
                     let mod0: IRInstructionModifier = match &name[3..name.len()]
                     {
                         "r" => { IRInstructionModifier::Register (get_reg(0,    "jifr")?) },
                         "m" => { IRInstructionModifier::Memory   (get_imm(0, 0, "jifm")?) },
                         "i" => { IRInstructionModifier::Immediate(get_imm(0, 0, "jifi")?) },
+                        "p" => { relative = true; IRInstructionModifier::Immediate(get_imm(0, 0, "jifp")?) },
                          _  => return Err(err_unknown()),
                     };
 
@@ -1377,7 +1495,11 @@ pub mod __asm
 
                     //let f = get_imm(1, _inner_offset, "jif")? as u8;
 
-                    (IRInstruction::JIF(mod0, f), debug)
+                    match mod0
+                    {
+                        IRInstructionModifier::Immediate(o) if relative => (IRInstruction::JIFREL(o, f), debug),
+                        _ => (IRInstruction::JIF(mod0, f), debug),
+                    }
 
                 }
                 else if(name.starts_with("cal"))
@@ -1393,16 +1515,20 @@ pub mod __asm
                         "r" => IRInstructionModifier::Register (get_reg(0,    "calr")?),
                         "m" => IRInstructionModifier::Memory   (get_imm(0, 0, "calm")?),
                         "i" => IRInstructionModifier::Immediate(get_imm(0, 0, "cali")?),
+                        "p" => return Ok((IRInstruction::CALREL(get_imm(0, 0, "calp")?), debug)),
                          _  => return Err(err_unknown()),
                     };
 
                     (IRInstruction::CAL(mod0), debug)
 
                 }
-                else if(name.starts_with("not") || name.starts_with("cmp"))
+                else if(name.starts_with("not") || name.starts_with("cmp") || name.starts_with("fcmp"))
                 {
 
-                    let __base = if(name.starts_with("not")) { "not" } else { "cmp" };
+                    let __base = 
+                             if(name.starts_with( "not")) {  "not" } 
+                        else if(name.starts_with("fcmp")) { "fcmp" } 
+                        else                              {  "cmp" };
 
                     let ins = |m|format!("{}{}",__base,m);
 
@@ -1411,7 +1537,7 @@ pub mod __asm
                         return Err(err_expect_args(__base, 2));
                     }
 
-                    let args = name[3..name.len()].to_string();
+                    let args = name[__base.len()..name.len()].to_string();
 
                     let mut mem_byte_off = if(args.contains('r')) { 1 } else { 0 };
 
@@ -1446,6 +1572,10 @@ pub mod __asm
                         { 
                             _IRALUInstruction2::NOT(( mod0, mod1 ))
                         } 
+                        else if(name.starts_with("fcmp")) 
+                        { 
+                            _IRALUInstruction2::FCMP(( mod0, mod1 ))
+                        } 
                         else 
                         { 
                             _IRALUInstruction2::CMP(( mod0, mod1 ))
@@ -1465,6 +1595,7 @@ pub mod __asm
 
                     if(name.starts_with("nand")) { offset = 4; }
                     if(name.starts_with(  "or")) { offset = 2; }
+                    if(name.starts_with("fadd") || name.starts_with("fsub") || name.starts_with("fmul") || name.starts_with("fdiv")) { offset = 4; }
                     
                     let __base = name[0..offset].to_string();
 
@@ -1549,6 +1680,10 @@ pub mod __asm
                         else if(name.starts_with( "shr")) { _IRALUInstruction3:: SHR(_modifiers) }
                         else if(name.starts_with("nand")) { _IRALUInstruction3::NAND(_modifiers) }
                         else if(name.starts_with( "nor")) { _IRALUInstruction3:: NOR(_modifiers) }
+                        else if(name.starts_with("fadd")) { _IRALUInstruction3::FADD(_modifiers) }
+                        else if(name.starts_with("fsub")) { _IRALUInstruction3::FSUB(_modifiers) }
+                        else if(name.starts_with("fmul")) { _IRALUInstruction3::FMUL(_modifiers) }
+                        else if(name.starts_with("fdiv")) { _IRALUInstruction3::FDIV(_modifiers) }
                         else { return Err(err_unknown()); }
                     )), debug)
 
@@ -1689,18 +1824,81 @@ pub mod __asm
                 },
                 _ => 
                 {
+                    let request_count = self.requested_labels.len();
                     let ins = self.expression_to_instruction(exp, off)?;
-                    ins_to_bytes(ins.0, push)?;
+                    let relative = matches!(ins.0, 
+                        IRInstruction::JMP(IRInstructionModifier::Immediate(_)) |
+                        IRInstruction::JIF(IRInstructionModifier::Immediate(_), _) |
+                        IRInstruction::CAL(IRInstructionModifier::Immediate(_)) |
+                        IRInstruction::JMPREL(_) | IRInstruction::JIFREL(_, _) | IRInstruction::CALREL(_) | IRInstruction::LEAREL(_, _)
+                    );
+                    let mut len = 0;
+                    ins_to_bytes(ins.0.clone(), |b| { len += 1; push(b) })?;
+                    if(relative && self.requested_labels.len() > request_count)
+                    {
+                        self.relative_requests.push((request_count, off, off + len, ins.0));
+                    }
                     Ok(ins.1)
                 }
             }
 
         }
 
+        /// jumps to labels of the same section get encoded pc relative, so the code runs wherever it is loaded
+        /// jumps to other labels stay absolute, explicit pc relative ones get resolved by the linker
+        fn resolve_relative_requests(&mut self, section: &mut SectionFormat) -> Result<(), Error>
+        {
+
+            let mut resolved: Vec<usize> = Vec::new();
+
+            for (i, start, end, ins) in std::mem::take(&mut self.relative_requests)
+            {
+
+                let req = &mut self.requested_labels[i];
+
+                let label = match section.labels.iter().find(|l| l.name == req.name)
+                {
+                    Some(l) => l,
+                    None =>
+                    {
+                        if(!matches!(ins, IRInstruction::JMP(_) | IRInstruction::JIF(_, _) | IRInstruction::CAL(_)))
+                        {
+                            req.rel = Some(end as u32);
+                        }
+                        continue;
+                    },
+                };
+
+                let o = (label.pos - end as i64) as u32;
+
+                let ins = match ins
+                {
+                    IRInstruction::JMP(_)    | IRInstruction::JMPREL(_)    => IRInstruction::JMPREL(o),
+                    IRInstruction::JIF(_, f) | IRInstruction::JIFREL(_, f) => IRInstruction::JIFREL(o, f),
+                    IRInstruction::CAL(_)    | IRInstruction::CALREL(_)    => IRInstruction::CALREL(o),
+                    IRInstruction::LEAREL(_, r) => IRInstruction::LEAREL(o, r),
+                    _ => unreachable!(),
+                };
+
+                let mut p = start;
+                ins_to_bytes(ins, |b| { section.section.data[p] = b; p += 1; Ok(()) })?;
+
+                resolved.push(i);
+
+            }
+
+            let mut i = 0;
+            self.requested_labels.retain(|_| { i += 1; !resolved.contains(&(i - 1)) });
+
+            Ok(())
+
+        }
+
         fn parse_section(&mut self, statement: Option<(Statement, DebugSymbol)>, all_labels: &mut Vec<Label>) -> Result<(SectionFormat, Option<(Statement, DebugSymbol)>), Error>
         {
 
             self.requested_labels = Vec::new();
+            self.relative_requests = Vec::new();
 
             let mut section = SectionFormat
             {
@@ -1754,6 +1952,8 @@ pub mod __asm
 
             self.director.switched_section = false;
 
+            self.resolve_relative_requests(&mut section)?;
+
             section.requested_labels = std::mem::take(&mut self.requested_labels);
             
             Ok((section, statement))
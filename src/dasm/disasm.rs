@@ -155,6 +155,11 @@ impl DASM
                 let m = self.parse_mod2(m, true);
                 format!("cmp{} {}, {}", m.0, m.1, m.2)
             },
+            _IRALUInstruction2::FCMP(m) =>
+            {
+                let m = self.parse_mod2(m, true);
+                format!("fcmp{} {}, {}", m.0, m.1, m.2)
+            },
         }
     }
     fn _parse_alu3(&mut self, ins: _IRALUInstruction3) -> String
@@ -174,6 +179,10 @@ impl DASM
              _IRALUInstruction3:: SHR(m) => ( "shr", m ),
              _IRALUInstruction3::NAND(m) => ("nand", m ),
              _IRALUInstruction3:: NOR(m) => ( "nor", m ),
+             _IRALUInstruction3::FADD(m) => ("fadd", m ),
+             _IRALUInstruction3::FSUB(m) => ("fsub", m ),
+             _IRALUInstruction3::FMUL(m) => ("fmul", m ),
+             _IRALUInstruction3::FDIV(m) => ("fdiv", m ),
         };
 
         let m = if m.is_some()
@@ -197,13 +206,16 @@ impl DASM
             IRALUInstruction::Complex (i) => self._parse_alu3(i),
         }
     }
-    fn ir_to_line(&mut self, ir: IRInstruction, loc: u32) -> String
+    fn ir_to_line(&mut self, ir: IRInstruction, loc: u32, next: u32) -> String
     {
         let ins = match ir
         {
             IRInstruction::DATA(_) => String::new(),
             IRInstruction::NOP => "nop".to_string(),
             IRInstruction::HLT => "hlt".to_string(),
+            IRInstruction::WFI => "wfi".to_string(),
+            IRInstruction::SYSCALL => "syscall".to_string(),
+            IRInstruction::SYSRET => "sysret".to_string(),
             IRInstruction::CLF => "clf".to_string(),
             IRInstruction::RET => "ret".to_string(),
             IRInstruction::DBG => "dbg".to_string(),
@@ -212,6 +224,14 @@ impl DASM
             IRInstruction::LEA(r) => format!("lea {}", DASM::reg_to_str(r)),
             IRInstruction::INC(r) => format!("inc {}", DASM::reg_to_str(r)),
             IRInstruction::DEC(r) => format!("dec {}", DASM::reg_to_str(r)),
+            IRInstruction::ITOF (r) => format!("itof {}", DASM::reg_to_str(r)),
+            IRInstruction::FTOI (r) => format!("ftoi {}", DASM::reg_to_str(r)),
+            IRInstruction::FSQRT(r) => format!("fsqrt {}", DASM::reg_to_str(r)),
+            IRInstruction::MEMCPY(rd, rs, rl) => format!("memcpy {}, {}, {}", DASM::reg_to_str(rd), DASM::reg_to_str(rs), DASM::reg_to_str(rl)),
+            IRInstruction::MEMSET(rd, rv, rl) => format!("memset {}, {}, {}", DASM::reg_to_str(rd), DASM::reg_to_str(rv), DASM::reg_to_str(rl)),
+            IRInstruction::XCHG(rm, rv) => format!("xchg {}, {}", DASM::reg_to_str(rm), DASM::reg_to_str(rv)),
+            IRInstruction::CAS (rm, re, rn) => format!("cas {}, {}, {}", DASM::reg_to_str(rm), DASM::reg_to_str(re), DASM::reg_to_str(rn)),
+            IRInstruction::XADD(rm, rv) => format!("xadd {}, {}", DASM::reg_to_str(rm), DASM::reg_to_str(rv)),
             IRInstruction::SER_IO(imm) => format!("__io {imm:#04x}"),
             IRInstruction::PSHFLG => "psgflg".to_string(),
             IRInstruction::POPFLG => "popflg".to_string(),
@@ -255,6 +275,19 @@ impl DASM
                 let m = self.parse_mod(m, true);
                 format!("cal{} {}", m.0, m.1)
             },
+            IRInstruction::JMPREL(o) => format!("jmpp {}", self.get_label_for(next.wrapping_add(o))),
+            IRInstruction::JIFREL(o, f) =>
+            {
+                let mut flags = String::new();
+                if(f & FLAG_C != 0) { flags.push('C'); }
+                if(f & FLAG_Z != 0) { flags.push('Z'); }
+                if(f & FLAG_B != 0) { flags.push('B'); }
+                if(f & FLAG_A != 0) { flags.push('A'); }
+                if(f & FLAG_E != 0) { flags.push('E'); }
+                format!("jifp {}, {}", self.get_label_for(next.wrapping_add(o)), flags)
+            },
+            IRInstruction::CALREL(o) => format!("calp {}", self.get_label_for(next.wrapping_add(o))),
+            IRInstruction::LEAREL(o, r) => format!("leap {}, {}", self.get_label_for(next.wrapping_add(o)), DASM::reg_to_str(r)),
             IRInstruction::ALU(ins) => self._parse_alu(ins),
         };
         format!("[{loc:#06x}] {ins}")
@@ -280,7 +313,8 @@ impl DASM
             Ok(ir) => ir,
             Err(e) => return ( Err(e), read_bytes ),
         };
-        ( Ok(self.ir_to_line(ir, loc)), read_bytes )
+        let next = loc + read_bytes.len() as u32;
+        ( Ok(self.ir_to_line(ir, loc, next)), read_bytes )
     }
 
     pub fn disassemble(&mut self) -> Result<String, Error>
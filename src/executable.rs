@@ -24,6 +24,8 @@ pub mod __internal
         pub name: String,
         pub loc: SourceLocation,
         pub pos: u32,
+        /// end of the requesting instruction if the label has to be written pc relative
+        pub rel: Option<u32>,
     }
 
     #[derive(Debug, PartialEq, Clone, Copy)]
@@ -193,6 +193,7 @@ pub enum _IRALUInstruction2
 
     NOT(IRInstructionModifier2),
     CMP(IRInstructionModifier2),
+   FCMP(IRInstructionModifier2),
 
 }
 
@@ -214,6 +215,11 @@ pub enum _IRALUInstruction3
    NAND(IRALUInstructionModifier3),
     NOR(IRALUInstructionModifier3),
 
+   FADD(IRALUInstructionModifier3),
+   FSUB(IRALUInstructionModifier3),
+   FMUL(IRALUInstructionModifier3),
+   FDIV(IRALUInstructionModifier3),
+
 }
 
 #[derive(Debug, Clone)]
@@ -237,6 +243,9 @@ pub enum IRInstruction
     PSHFLG,
     POPFLG,
     INT(IRImmediate),
+    WFI, // wait for interrupt
+    SYSCALL, // syscall on the kernel stack
+    SYSRET,
     DBG, // debug instruction
 
     // every mov instruction
@@ -252,11 +261,31 @@ pub enum IRInstruction
     CAL(IRInstructionModifier), // call
     RET,
 
+    // pc relative instructions [offset counts from the start of the next instruction]
+    JMPREL(IRImmediate),
+    JIFREL(IRImmediate, IRJIFFlags),
+    CALREL(IRImmediate),
+    LEAREL(IRImmediate, IRRegister),
+
     INC(IRRegister),
     DEC(IRRegister),
     // alu instructions
     ALU(IRALUInstruction),
 
+    // float instructions [registers hold raw f32 bits]
+    ITOF(IRRegister),
+    FTOI(IRRegister),
+    FSQRT(IRRegister),
+
+    // bulk memory instructions [dst, src/val, len]
+    MEMCPY(IRRegister, IRRegister, IRRegister),
+    MEMSET(IRRegister, IRRegister, IRRegister),
+
+    // atomic instructions [first register holds the address of a 32b value]
+    XCHG(IRRegister, IRRegister),
+    CAS (IRRegister, IRRegister, IRRegister),
+    XADD(IRRegister, IRRegister),
+
     DATA(Vec<u8>),
 
 }
@@ -305,6 +334,15 @@ pub mod _IRBinaryHeader
             let b = u32_2_u8(w);
             self.put8(b.0).put8(b.1).put8(b.2).put8(b.3)
         }
+        /// 0 terminated, has to fit into the rest of the header
+        pub fn put_str(&mut self, s: &str) -> &mut Self
+        {
+            for b in s.bytes()
+            {
+                self.put8(b);
+            }
+            self.put8(0)
+        }
         pub fn finish(&mut self) -> [u8; 32]
         {
             for i in self.pos..32
@@ -359,6 +397,15 @@ pub mod _IRBinaryHeader
             *w = u8_2_u32((b0, b1,b2,b3));
             self
         }
+        pub fn get_str(&mut self, s: &mut String) -> &mut Self
+        {
+            while(self.pos < 32 && self.bytes[self.pos] != 0)
+            {
+                s.push(self.bytes[self.pos] as char);
+                self.pos += 1;
+            }
+            self
+        }
     }
 
     #[derive(Debug)]
@@ -407,12 +454,70 @@ pub mod _IRBinaryHeader
         }
     }
 
+    /// V_0000 + the files location the vm sets as file system root on load
+    #[derive(Debug)]
+    pub struct _V_0001
+    {
+        pub entry_point: u32,
+        pub stack_adr: u32,
+        pub stack_size: u32,
+        pub flags: u8,
+        pub file_loc: String,
+    }
+    impl _V_0001
+    {
+        /// whats left of the 32 bytes after the fixed fields and the 0 terminator
+        pub const MAX_FILE_LOC: usize = 16;
+    }
+    impl __IRBinaryHeader for _V_0001
+    {
+        fn serialize(&self) -> [u8; 32] 
+        {
+            Serializer::new()
+                .put16(0x0001)
+                .put32(self.entry_point)
+                .put32(self.stack_adr)
+                .put32(self.stack_size)
+                .put8(self.flags)
+                .put_str(&self.file_loc)
+            .finish()
+        }
+        fn deserialize(bytes: [u8; 32]) -> Self
+        {
+            
+            let mut entry_point: u32 = 0;
+            let mut stack_adr: u32 = 0;
+            let mut stack_size: u32 = 0;
+            let mut flags: u8 = 0;
+            let mut file_loc = String::new();
+
+            Deserializer::new(bytes)
+                .skip(2)
+                .get32(&mut entry_point)
+                .get32(&mut stack_adr)
+                .get32(&mut stack_size)
+                .get8(&mut flags)
+                .get_str(&mut file_loc);
+
+            Self
+            {
+                entry_point,
+                stack_adr,
+                stack_size,                
+                flags,
+                file_loc,
+            }
+
+        }
+    }
+
 }
 
 #[derive(Debug)]
 pub enum IRBinaryHeader
 {
     V_0000(_IRBinaryHeader::_V_0000),
+    V_0001(_IRBinaryHeader::_V_0001),
 }
 impl IRBinaryHeader
 {
@@ -421,6 +526,7 @@ impl IRBinaryHeader
         match ver
         {
             0x0000 => IRBinaryHeader::V_0000(_IRBinaryHeader::_V_0000::deserialize(bytes)),
+            0x0001 => IRBinaryHeader::V_0001(_IRBinaryHeader::_V_0001::deserialize(bytes)),
             _ => panic!("FATAL INVALID BINARY HEADER VERSION {:#x}", ver),
         }
     }
@@ -432,6 +538,7 @@ impl __IRBinaryHeader for IRBinaryHeader
         match self
         {
             IRBinaryHeader::V_0000(v0000) => v0000.serialize(),
+            IRBinaryHeader::V_0001(v0001) => v0001.serialize(),
         }
     }
     fn deserialize(bytes: [u8; 32]) -> Self
@@ -608,8 +715,15 @@ pub mod helpers
             self.constructing = true;
             match self.version
             {
-                0x0001 => self.file_loc = loc_str,
-                _ =>  return Err(error_in!(loc, "Cannot set entry point in header version {:#x}!", self.version)),
+                0x0001 =>
+                {
+                    if(loc_str.len() > _IRBinaryHeader::_V_0001::MAX_FILE_LOC)
+                    {
+                        return Err(error_in!(loc, "Files location '{}' is longer than {} bytes!", loc_str, _IRBinaryHeader::_V_0001::MAX_FILE_LOC));
+                    }
+                    self.file_loc = loc_str
+                },
+                _ =>  return Err(error_in!(loc, "Cannot set files location in header version {:#x}!", self.version)),
             };
             Ok(())
         }
@@ -638,6 +752,16 @@ pub mod helpers
                         stack_size: self.stack_size,
                     })
                 },
+                0x0001 => 
+                {
+                    IRBinaryHeader::V_0001(_IRBinaryHeader::_V_0001{
+                        entry_point: self._entry,
+                        flags: self.flags,
+                        stack_adr: self.stack_pos,
+                        stack_size: self.stack_size,
+                        file_loc: self.file_loc.clone(),
+                    })
+                },
                 _ => return Err(error!("Invalid binary header version: {:#x}", self.version)),
             })
 
@@ -665,6 +789,7 @@ pub mod _instruction_conversion
         {
             _IRALUInstruction2::NOT(m) => (0x60, m),
             _IRALUInstruction2::CMP(m) => (0x64, m),
+            _IRALUInstruction2::FCMP(m) => (0x68, m),
         };
 
         match m.0
@@ -746,6 +871,10 @@ pub mod _instruction_conversion
             _IRALUInstruction3:: SHR(m) => (0x79, m),
             _IRALUInstruction3::NAND(m) => (0x7A, m),
             _IRALUInstruction3:: NOR(m) => (0x7B, m),
+            _IRALUInstruction3::FADD(m) => (0x7C, m),
+            _IRALUInstruction3::FSUB(m) => (0x7D, m),
+            _IRALUInstruction3::FMUL(m) => (0x7E, m),
+            _IRALUInstruction3::FDIV(m) => (0x7F, m),
         };
 
         let m = match m
@@ -933,6 +1062,9 @@ pub mod _instruction_conversion
     
             IRInstruction::NOP => push(0x00)?,
             IRInstruction::HLT => push(0x01)?,
+            IRInstruction::WFI => push(0x0A)?,
+            IRInstruction::SYSCALL => push(0x0B)?,
+            IRInstruction::SYSRET  => push(0x0C)?,
             IRInstruction::CLF => push(0x02)?,
             IRInstruction::LEA(r) => { push(0x03)?; push(reg_to_byte(r))?; },
             IRInstruction::DBG => push(0x0F)?,
@@ -1347,10 +1479,26 @@ pub mod _instruction_conversion
 
             },
             IRInstruction::RET => push(0x4F)?,
+
+            IRInstruction::JMPREL(o) => { push(0x59)?; let o = u32_2_u8(o); push(o.0)?; push(o.1)?; push(o.2)?; push(o.3)?; },
+            IRInstruction::JIFREL(o, f) => { push(0x5A)?; let o = u32_2_u8(o); push(o.0)?; push(o.1)?; push(o.2)?; push(o.3)?; push(f)?; },
+            IRInstruction::CALREL(o) => { push(0x5B)?; let o = u32_2_u8(o); push(o.0)?; push(o.1)?; push(o.2)?; push(o.3)?; },
+            IRInstruction::LEAREL(o, r) => { push(0x5C)?; let o = u32_2_u8(o); push(o.0)?; push(o.1)?; push(o.2)?; push(o.3)?; push(reg_to_byte(r))?; },
        
             IRInstruction::INC(r) => { push(0x6E)?; push(reg_to_byte(r))?; },
             IRInstruction::DEC(r) => { push(0x6F)?; push(reg_to_byte(r))?; },
 
+            IRInstruction::ITOF (r) => { push(0x6C)?; push(reg_to_byte(r))?; },
+            IRInstruction::FTOI (r) => { push(0x6D)?; push(reg_to_byte(r))?; },
+            IRInstruction::FSQRT(r) => { push(0x09)?; push(reg_to_byte(r))?; },
+
+            IRInstruction::MEMCPY(rd, rs, rl) => { push(0x3E)?; push(combine_regs(reg_to_byte(rd), reg_to_byte(rs)))?; push(reg_to_byte(rl))?; },
+            IRInstruction::MEMSET(rd, rv, rl) => { push(0x3F)?; push(combine_regs(reg_to_byte(rd), reg_to_byte(rv)))?; push(reg_to_byte(rl))?; },
+
+            IRInstruction::XCHG(rm, rv) => { push(0x1E)?; push(combine_regs(reg_to_byte(rm), reg_to_byte(rv)))?; },
+            IRInstruction::CAS (rm, re, rn) => { push(0x1F)?; push(combine_regs(reg_to_byte(rm), reg_to_byte(re)))?; push(reg_to_byte(rn))?; },
+            IRInstruction::XADD(rm, rv) => { push(0x2E)?; push(combine_regs(reg_to_byte(rm), reg_to_byte(rv)))?; },
+
             IRInstruction::ALU(alu_ins) => return alu_ins_to_bytes(alu_ins, push),
 
         };
@@ -1397,11 +1545,26 @@ pub mod _instruction_conversion
                 0x06 => return Ok(IRInstruction::SER_IO(fetch()? as IRImmediate)),
                 0x07 => return Ok(IRInstruction::PSHFLG),
                 0x08 => return Ok(IRInstruction::POPFLG),
+                0x0A => return Ok(IRInstruction::WFI),
+                0x0B => return Ok(IRInstruction::SYSCALL),
+                0x0C => return Ok(IRInstruction::SYSRET),
                 0x0E => return Ok(IRInstruction::INT(fetch()? as IRImmediate)),
                 0x0F => return Ok(IRInstruction::DBG),
 
+                0x3E => { let (rd, rs) = get2reg(&mut fetch)?; return Ok(IRInstruction::MEMCPY(rd, rs, get_reg(&mut fetch)?)); },
+                0x3F => { let (rd, rv) = get2reg(&mut fetch)?; return Ok(IRInstruction::MEMSET(rd, rv, get_reg(&mut fetch)?)); },
+
+                0x1E => { let (rm, rv) = get2reg(&mut fetch)?; return Ok(IRInstruction::XCHG(rm, rv)); },
+                0x1F => { let (rm, re) = get2reg(&mut fetch)?; return Ok(IRInstruction::CAS(rm, re, get_reg(&mut fetch)?)); },
+                0x2E => { let (rm, rv) = get2reg(&mut fetch)?; return Ok(IRInstruction::XADD(rm, rv)); },
+
                 0x4F => return Ok(IRInstruction::RET),
 
+                0x59 => return Ok(IRInstruction::JMPREL(fetch_dword(&mut fetch)?)),
+                0x5A => { let o = fetch_dword(&mut fetch)?; return Ok(IRInstruction::JIFREL(o, fetch()?)); },
+                0x5B => return Ok(IRInstruction::CALREL(fetch_dword(&mut fetch)?)),
+                0x5C => { let o = fetch_dword(&mut fetch)?; return Ok(IRInstruction::LEAREL(o, get_reg(&mut fetch)?)); },
+
                 _ => {},
 
             };
@@ -1517,6 +1680,10 @@ pub mod _instruction_conversion
                             0x9 => _IRALUInstruction3:: SHR (modifiers),
                             0xA => _IRALUInstruction3::NAND (modifiers),
                             0xB => _IRALUInstruction3:: NOR (modifiers),
+                            0xC => _IRALUInstruction3::FADD (modifiers),
+                            0xD => _IRALUInstruction3::FSUB (modifiers),
+                            0xE => _IRALUInstruction3::FMUL (modifiers),
+                            0xF => _IRALUInstruction3::FDIV (modifiers),
 
                             _ => return Err(error!("FATAL: INVALID INSTRUCTION! ({:#x})", (ins & 0xF))),
 
@@ -1539,10 +1706,21 @@ pub mod _instruction_conversion
                             let reg = get_reg(&mut fetch)?;
                             return Ok(IRInstruction::DEC(reg));
                         },
+                        0x6C =>
+                        {
+                            let reg = get_reg(&mut fetch)?;
+                            return Ok(IRInstruction::ITOF(reg));
+                        },
+                        0x6D =>
+                        {
+                            let reg = get_reg(&mut fetch)?;
+                            return Ok(IRInstruction::FTOI(reg));
+                        },
                         _ => {},
                     }
                     
                     let mut not_ins = true;
+                    let mut fcmp_ins = false;
 
                     let modifiers: IRInstructionModifier2 = match ins
                     {
@@ -1623,6 +1801,50 @@ pub mod _instruction_conversion
                             )
                         }, //cmp mm
 
+                        0x68 => 
+                        {
+                            not_ins = false;
+                            fcmp_ins = true;
+                            let regs = get2reg(&mut fetch)?;
+                            (
+                                IRInstructionModifier::Register(regs.0),
+                                IRInstructionModifier::Register(regs.1),
+                            )
+                        }, //fcmp rr
+                        0x69 => 
+                        {
+                            not_ins = false;
+                            fcmp_ins = true;
+                            let reg = get_reg     (&mut fetch)?;
+                            let mem = fetch_dword (&mut fetch)?;
+                            ( 
+                                IRInstructionModifier::Memory   (mem),
+                                IRInstructionModifier::Register (reg),
+                            )
+                        }, //fcmp mr
+                        0x6A => 
+                        {
+                            not_ins = false;
+                            fcmp_ins = true;
+                            let reg = get_reg     (&mut fetch)?;
+                            let mem = fetch_dword (&mut fetch)?;
+                            ( 
+                                IRInstructionModifier::Register (reg),
+                                IRInstructionModifier::Memory   (mem),
+                            )
+                        }, //fcmp rm
+                        0x6B => 
+                        {
+                            not_ins = false;
+                            fcmp_ins = true;
+                            let mem0 = fetch_dword (&mut fetch)?;
+                            let mem1 = fetch_dword (&mut fetch)?;
+                            ( 
+                                IRInstructionModifier::Memory   (mem0),
+                                IRInstructionModifier::Memory   (mem1),
+                            )
+                        }, //fcmp mm
+
                         _ => return Err(error!("FATAL: INVALID INSTRUCTION MODIFIER! ({:#x})", ins)),
 
                     };
@@ -1632,6 +1854,10 @@ pub mod _instruction_conversion
                         {
                             _IRALUInstruction2::NOT(modifiers)
                         }
+                        else if(fcmp_ins)
+                        {
+                            _IRALUInstruction2::FCMP(modifiers)
+                        }
                         else
                         {
                             _IRALUInstruction2::CMP(modifiers)
@@ -1970,6 +2196,10 @@ pub mod _instruction_conversion
                     {
                         IRInstruction::SER_IN(reg)
                     }
+                    else if(ins == 0x09)
+                    {
+                        IRInstruction::FSQRT(reg)
+                    }
                     else
                     {
                         return Err(error!("Error no instruction {:#04x}!", ins));
@@ -1984,6 +2214,77 @@ pub mod _instruction_conversion
     pub fn bytes_to_ins(fetch: impl FnMut() -> Result<u8, Error>) -> Result<IRInstruction, Error>
     { bytes_to_repr::bytes_to_ins(fetch) }
 
+    #[cfg(test)]
+    mod tests
+    {
+
+        use super::*;
+        use IRInstructionModifier::{Register, Memory};
+
+        /// encodes, decodes again and checks nothing changed and every byte was used
+        fn round_trip(ins: IRInstruction) -> Vec<u8>
+        {
+            let mut bytes: Vec<u8> = Vec::new();
+            ins_to_bytes(ins.clone(), |b| { bytes.push(b); Ok(()) }).unwrap();
+            let mut pos = 0;
+            let back = bytes_to_ins(||
+            {
+                pos += 1;
+                bytes.get(pos - 1).copied().ok_or(error!("ran out of bytes"))
+            }).unwrap();
+            assert_eq!(pos, bytes.len(), "{:?} left bytes over", ins);
+            assert_eq!(format!("{:?}", back), format!("{:?}", ins));
+            bytes
+        }
+
+        #[test]
+        fn system_instructions()
+        {
+            assert_eq!(round_trip(IRInstruction::WFI), [0x0A]);
+            assert_eq!(round_trip(IRInstruction::SYSCALL), [0x0B]);
+            assert_eq!(round_trip(IRInstruction::SYSRET), [0x0C]);
+        }
+
+        #[test]
+        fn relative_instructions()
+        {
+            // offsets are signed, backwards ones have to survive as well
+            assert_eq!(round_trip(IRInstruction::JMPREL(-7i32 as u32)), [0x59, 0xFF, 0xFF, 0xFF, 0xF9]);
+            assert_eq!(round_trip(IRInstruction::JIFREL(0x10, 0x03)), [0x5A, 0x00, 0x00, 0x00, 0x10, 0x03]);
+            assert_eq!(round_trip(IRInstruction::CALREL(0x12345678)), [0x5B, 0x12, 0x34, 0x56, 0x78]);
+            assert_eq!(round_trip(IRInstruction::LEAREL(-1i32 as u32, IRRegister::R5)), [0x5C, 0xFF, 0xFF, 0xFF, 0xFF, 0x08]);
+        }
+
+        #[test]
+        fn float_instructions()
+        {
+            round_trip(IRInstruction::ITOF(IRRegister::R1));
+            round_trip(IRInstruction::FTOI(IRRegister::R9));
+            round_trip(IRInstruction::FSQRT(IRRegister::RD));
+            round_trip(IRInstruction::ALU(IRALUInstruction::Simple(_IRALUInstruction2::FCMP(( Register(IRRegister::RA), Register(IRRegister::RB) )))));
+            round_trip(IRInstruction::ALU(IRALUInstruction::Simple(_IRALUInstruction2::FCMP(( Memory(0x100), Memory(0x200) )))));
+            let ops: [fn(IRALUInstructionModifier3) -> _IRALUInstruction3; 4] =
+                [_IRALUInstruction3::FADD, _IRALUInstruction3::FSUB, _IRALUInstruction3::FMUL, _IRALUInstruction3::FDIV];
+            for op in ops
+            {
+                round_trip(IRInstruction::ALU(IRALUInstruction::Complex(op(None))));
+                round_trip(IRInstruction::ALU(IRALUInstruction::Complex(op(Some(( Register(IRRegister::RA), Register(IRRegister::RB), Register(IRRegister::RC) ))))));
+                round_trip(IRInstruction::ALU(IRALUInstruction::Complex(op(Some(( Register(IRRegister::R4), Register(IRRegister::R5), Memory(0xCAFE) ))))));
+            }
+        }
+
+        #[test]
+        fn memory_instructions()
+        {
+            assert_eq!(round_trip(IRInstruction::MEMCPY(IRRegister::RA, IRRegister::RB, IRRegister::RC)), [0x3E, 0x01, 0x02]);
+            assert_eq!(round_trip(IRInstruction::MEMSET(IRRegister::R4, IRRegister::R5, IRRegister::R6)), [0x3F, 0x78, 0x09]);
+            assert_eq!(round_trip(IRInstruction::XCHG(IRRegister::R1, IRRegister::R2)), [0x1E, 0x45]);
+            assert_eq!(round_trip(IRInstruction::CAS(IRRegister::RA, IRRegister::RD, IRRegister::R9)), [0x1F, 0x03, 0x0C]);
+            assert_eq!(round_trip(IRInstruction::XADD(IRRegister::R8, IRRegister::R7)), [0x2E, 0xBA]);
+        }
+
+    }
+
 }
 
 
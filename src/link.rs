@@ -324,7 +324,12 @@ pub mod __link
                         };
 
                         let adr = req.pos as usize + l.2;
-                        let v = u32_2_u8(label.pos as u32);
+                        let pos = match req.rel
+                        {
+                            Some(end) => label.pos - (end as usize + l.2) as i64,
+                            None => label.pos,
+                        };
+                        let v = u32_2_u8(pos as u32);
 
                         code_section[adr    ] = v.0;
                         code_section[adr + 1] = v.1;
@@ -523,6 +528,68 @@ pub mod __link
 
     }
 
+    #[cfg(test)]
+    mod tests
+    {
+
+        use super::*;
+        use crate::instructions::SourceLocation;
+
+        fn label(name: &str, pos: i64) -> Label
+        {
+            Label { name: name.to_string(), fileloc: SourceLocation::new(), pos }
+        }
+        fn code(data: Vec<u8>, labels: Vec<Label>, exposed_labels: Vec<Label>, requested_labels: Vec<LabelRequest>) -> SectionFormat
+        {
+            SectionFormat
+            {
+                section: SectionData { section: Section::Code, data },
+                labels,
+                exposed_labels,
+                requested_labels,
+                symbols: Vec::new(),
+            }
+        }
+
+        #[test]
+        fn relative_requests_count_from_the_end_of_the_instruction()
+        {
+
+            // a: 8 bytes of code, exposes "start" at its beginning
+            let a = Format
+            {
+                sections: vec![code(vec![0; 8], vec![label("start", 0)], vec![label("start", 0)], Vec::new())],
+                external_labels: Vec::new(),
+                header: None,
+            };
+            // b: nop, nop, jmpp start, jmpp back; linked behind a, so every position moves by 8
+            let b = Format
+            {
+                sections: vec![code(
+                    vec![0x00, 0x00, 0x59, 0, 0, 0, 0, 0x59, 0, 0, 0, 0],
+                    vec![label("back", 0)],
+                    Vec::new(),
+                    vec![
+                        LabelRequest { name: "start".to_string(), loc: SourceLocation::new(), pos: 3, rel: Some(7) },
+                        LabelRequest { name: "back".to_string(), loc: SourceLocation::new(), pos: 8, rel: Some(12) },
+                    ],
+                )],
+                external_labels: vec![label("start", 0)],
+                header: None,
+            };
+
+            let (exe, _) = Linker::formats(vec![a, b]).link().unwrap();
+            let offset = |at: usize| u8_2_u32((exe.section_code[at], exe.section_code[at + 1], exe.section_code[at + 2], exe.section_code[at + 3])) as i32;
+
+            // start is at 0, the first jmpp ends at 8 + 7
+            assert_eq!(offset(11), -15);
+            // back is at 8 + 0, the second jmpp ends at 8 + 12
+            assert_eq!(offset(16), -12);
+
+        }
+
+    }
+
 }
 
 pub type Linker = __link::Linker;
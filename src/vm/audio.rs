@@ -0,0 +1,181 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+use std::f64::consts::TAU;
+
+pub const AUDIO_DEFAULT_RATE: u32 = 22050;
+const AUDIO_MIN_RATE: u32 = 1000;
+const AUDIO_MAX_RATE: u32 = 96000;
+// the whole recording stays in memory until the vm stops
+const AUDIO_MAX_SECONDS: u64 = 600;
+
+#[repr(u8)]
+#[derive(PartialEq)]
+pub enum AudioResult
+{
+    OK = 0,
+    InvalidFormat,
+    InvalidTone,
+    TooLong,
+}
+
+/// mono sound the guest queues up, one piece after the other
+/// -> everything is kept as 16 bit samples and written out as a wav file when the vm stops [--audio-wav]
+pub struct AUDIO
+{
+    rate: u32,
+    /// of the samples Queue() gets, 8 is unsigned, 16 signed big endian
+    bits: u32,
+    /// of tones, 0 to 255
+    volume: u32,
+    /// without a file to write to only the length is counted
+    recording: bool,
+    samples: Vec<i16>,
+    queued: u64,
+}
+impl Default for AUDIO
+{
+    fn default() -> Self { Self::new() }
+}
+impl AUDIO
+{
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            rate: AUDIO_DEFAULT_RATE,
+            bits: 8,
+            volume: 128,
+            recording: false,
+            samples: Vec::new(),
+            queued: 0,
+        }
+    }
+
+    pub fn Record(&mut self)
+    {
+        self.recording = true;
+    }
+
+    /// the rate can only change before anything was queued, a wav file has just one
+    pub fn SetFormat(&mut self, rate: u32, bits: u32) -> AudioResult
+    {
+        if(!(AUDIO_MIN_RATE..=AUDIO_MAX_RATE).contains(&rate) || (bits != 8 && bits != 16))
+        {
+            return AudioResult::InvalidFormat;
+        }
+        if(rate != self.rate && self.queued != 0)
+        {
+            return AudioResult::InvalidFormat;
+        }
+        self.rate = rate;
+        self.bits = bits;
+        AudioResult::OK
+    }
+    pub fn SetVolume(&mut self, volume: u32)
+    {
+        self.volume = volume.min(255);
+    }
+    pub fn Bytes(&self) -> u32 { self.bits / 8 }
+    /// samples queued so far, Queued() / rate is the length in seconds
+    pub fn Queued(&self) -> u32 { self.queued.min(u32::MAX as u64) as u32 }
+
+    /// whether count more samples stay under the length limit
+    pub fn Fits(&self, count: u64) -> bool
+    {
+        self.queued + count <= AUDIO_MAX_SECONDS * self.rate as u64
+    }
+
+    fn _push(&mut self, samples: impl Iterator<Item = i16>, count: u64) -> AudioResult
+    {
+        if(!self.Fits(count))
+        {
+            return AudioResult::TooLong;
+        }
+        self.queued += count;
+        if(self.recording)
+        {
+            self.samples.extend(samples);
+        }
+        AudioResult::OK
+    }
+
+    /// raw samples in the current format
+    pub fn Queue(&mut self, bytes: Vec<u8>) -> AudioResult
+    {
+        let step = self.Bytes() as usize;
+        let count = (bytes.len() / step) as u64;
+        let samples = bytes.chunks_exact(step).map(|s| match s
+        {
+            [b] => ((*b as i16) - 128) << 8,
+            [h, l] => i16::from_be_bytes([*h, *l]),
+            _ => unreachable!(),
+        });
+        self._push(samples, count)
+    }
+
+    /// wave 0 square, 1 sine; frequency 0 is silence
+    pub fn Tone(&mut self, wave: u32, frequency: u32, ms: u32) -> AudioResult
+    {
+
+        if(wave > 1 || frequency > self.rate / 2)
+        {
+            return AudioResult::InvalidTone;
+        }
+
+        let count = ms as u64 * self.rate as u64 / 1000;
+        let amplitude = self.volume as f64 / 255.0 * i16::MAX as f64;
+        let step = frequency as f64 / self.rate as f64;
+        let samples = (0..count).map(|i|
+        {
+            if(frequency == 0) { return 0; }
+            let phase = (i as f64 * step).fract();
+            let v = if(wave == 0)
+            {
+                if(phase < 0.5) { 1.0 } else { -1.0 }
+            }
+            else
+            {
+                (phase * TAU).sin()
+            };
+            (v * amplitude) as i16
+        });
+        self._push(samples, count)
+
+    }
+
+    /// 16 bit mono pcm
+    pub fn WriteWav(&self, path: &str) -> Result<(), Error>
+    {
+
+        let data = self.samples.len() as u32 * 2;
+        let mut out = Vec::with_capacity(44 + data as usize);
+        out.extend(b"RIFF");
+        out.extend((36 + data).to_le_bytes());
+        out.extend(b"WAVE");
+        out.extend(b"fmt ");
+        out.extend(16u32.to_le_bytes());
+        out.extend(1u16.to_le_bytes()); // pcm
+        out.extend(1u16.to_le_bytes()); // mono
+        out.extend(self.rate.to_le_bytes());
+        out.extend((self.rate * 2).to_le_bytes());
+        out.extend(2u16.to_le_bytes());
+        out.extend(16u16.to_le_bytes());
+        out.extend(b"data");
+        out.extend(data.to_le_bytes());
+        for s in &self.samples
+        {
+            out.extend(s.to_le_bytes());
+        }
+
+        match std::fs::write(path, out)
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(error!("Couldnt write audio '{}': {}", path, e)),
+        }
+
+    }
+
+}
@@ -0,0 +1,240 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::{*, helpers::*};
+use erebos::error;
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::{Duration, Instant};
+
+const CHANNEL_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// longest message either side may send, a longer length from the other vm closes the channel
+pub const CHANNEL_MAX_MESSAGE: u32 = 1024 * 1024;
+
+#[repr(u8)]
+#[derive(PartialEq)]
+pub enum ChannelResult
+{
+    OK = 0,
+    NotConnected,
+    NoMessage,
+    TooLong,
+    Closed,
+    CouldntSend,
+}
+
+/// message link to one other vm over a unix socket
+/// -> every message is its length [u32 big endian] and then the bytes, so they arrive exactly as sent
+pub struct CHANNEL
+{
+    stream: Option<UnixStream>,
+    /// complete messages that werent received yet
+    inbox: VecDeque<Vec<u8>>,
+    /// bytes of a message that didnt fully arrive yet
+    partial: Vec<u8>,
+    closed: bool,
+}
+impl Default for CHANNEL
+{
+    fn default() -> Self { Self::new() }
+}
+impl CHANNEL
+{
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            stream: None,
+            inbox: VecDeque::new(),
+            partial: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn _attach(&mut self, stream: UnixStream) -> Result<(), Error>
+    {
+        if let Err(e) = stream.set_nonblocking(true)
+        {
+            return Err(Error::fromio(e));
+        }
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// blocks till the other vm connects, a leftover socket file from an earlier run is replaced
+    pub fn Listen(&mut self, path: &str) -> Result<(), Error>
+    {
+
+        if let Ok(meta) = std::fs::symlink_metadata(path)
+        {
+            if(!meta.file_type().is_socket())
+            {
+                return Err(error!("Channel '{}' exists and is not a socket!", path));
+            }
+            _ = std::fs::remove_file(path);
+        }
+
+        let listener = match UnixListener::bind(path)
+        {
+            Ok(l) => l,
+            Err(e) => return Err(error!("Couldnt listen on channel '{}': {}", path, e)),
+        };
+        let stream = match listener.accept()
+        {
+            Ok((s, _)) => s,
+            Err(e) => return Err(error!("Couldnt accept on channel '{}': {}", path, e)),
+        };
+        _ = std::fs::remove_file(path);
+
+        self._attach(stream)
+
+    }
+
+    /// retries for a while, so both vms can be started at the same time
+    pub fn Connect(&mut self, path: &str) -> Result<(), Error>
+    {
+        let start = Instant::now();
+        loop
+        {
+            match UnixStream::connect(path)
+            {
+                Ok(s) => return self._attach(s),
+                Err(e) if start.elapsed() >= CHANNEL_CONNECT_TIMEOUT =>
+                {
+                    return Err(error!("Couldnt connect to channel '{}': {}", path, e));
+                },
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+
+    /// moves whatever arrived into the inbox
+    fn _pump(&mut self)
+    {
+
+        let mut buffer = [0u8; 4096];
+        while(!self.closed)
+        {
+            let Some(stream) = &mut self.stream
+            else { return; };
+            match stream.read(&mut buffer)
+            {
+                Ok(0) => { self.closed = true; break; },
+                Ok(n) => self.partial.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => { self.closed = true; break; },
+            }
+            // split right away, so partial never holds more than one message
+            self._split();
+        }
+
+    }
+    fn _split(&mut self)
+    {
+        while(self.partial.len() >= 4)
+        {
+            let len = u8_2_u32((self.partial[0], self.partial[1], self.partial[2], self.partial[3]));
+            if(len > CHANNEL_MAX_MESSAGE)
+            {
+                // the other vm doesnt play by the rules, whatever follows cant be trusted either
+                self.partial.clear();
+                self.closed = true;
+                return;
+            }
+            let len = len as usize;
+            if(self.partial.len() < 4 + len) { break; }
+            let message = self.partial[4..4 + len].to_vec();
+            self.partial.drain(..4 + len);
+            self.inbox.push_back(message);
+        }
+    }
+
+    pub fn Send(&mut self, message: Vec<u8>) -> ChannelResult
+    {
+
+        if(self.stream.is_none())
+        {
+            return ChannelResult::NotConnected;
+        }
+        if(self.closed)
+        {
+            return ChannelResult::Closed;
+        }
+
+        let len = u32_2_u8(message.len() as u32);
+        let mut frame = vec![len.0, len.1, len.2, len.3];
+        frame.extend(message);
+
+        let mut sent = 0;
+        while(sent < frame.len())
+        {
+            let Some(stream) = &mut self.stream
+            else { unreachable!() };
+            match stream.write(&frame[sent..])
+            {
+                Ok(0) => return ChannelResult::CouldntSend,
+                Ok(n) => sent += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) if e.kind() == ErrorKind::WouldBlock =>
+                {
+                    // the other side may be stuck sending to us, so keep reading while waiting
+                    self._pump();
+                    std::thread::sleep(Duration::from_millis(1));
+                },
+                Err(_) => return ChannelResult::CouldntSend,
+            }
+        }
+
+        ChannelResult::OK
+
+    }
+
+    /// a message longer than max stays in the inbox, TooLong gives its length
+    pub fn Receive(&mut self, max: u32) -> (ChannelResult, Option<Vec<u8>>, u32)
+    {
+
+        if(self.stream.is_none())
+        {
+            return (ChannelResult::NotConnected, None, 0);
+        }
+        self._pump();
+
+        let Some(front) = self.inbox.front()
+        else
+        {
+            return (if(self.closed) { ChannelResult::Closed } else { ChannelResult::NoMessage }, None, 0);
+        };
+
+        let len = front.len() as u32;
+        if(len > max)
+        {
+            return (ChannelResult::TooLong, None, len);
+        }
+
+        (ChannelResult::OK, self.inbox.pop_front(), len)
+
+    }
+
+    /// (messages waiting, length of the next one)
+    pub fn Poll(&mut self) -> (ChannelResult, u32, u32)
+    {
+        if(self.stream.is_none())
+        {
+            return (ChannelResult::NotConnected, 0, 0);
+        }
+        self._pump();
+        let next = self.inbox.front().map(|m| m.len() as u32).unwrap_or(0);
+        let result = if(self.inbox.is_empty() && self.closed) { ChannelResult::Closed } else { ChannelResult::OK };
+        (result, self.inbox.len() as u32, next)
+    }
+
+    pub fn HasMessage(&mut self) -> bool
+    {
+        self._pump();
+        !self.inbox.is_empty()
+    }
+
+}
@@ -0,0 +1,146 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use crossterm::{cursor, queue, style, terminal};
+use crossterm::style::Color;
+use std::io::{stdout, IsTerminal, Write};
+
+// what GetSize() gives when stdout isnt a terminal [or doesnt know its size]
+pub const CONSOLE_DEFAULT_COLS: u32 = 80;
+pub const CONSOLE_DEFAULT_ROWS: u32 = 25;
+
+/// terminal control for what __out prints
+/// -> if stdout isnt a terminal [piped, redirected] everything but GetSize() is ignored, so the output stays plain text
+pub struct CONSOLE
+{
+    tty: bool,
+    /// what Drop has to undo
+    cursor_hidden: bool,
+    colored: bool,
+    alternate: bool,
+}
+impl Default for CONSOLE
+{
+    fn default() -> Self { Self::new() }
+}
+impl CONSOLE
+{
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            tty: stdout().is_terminal(),
+            cursor_hidden: false,
+            colored: false,
+            alternate: false,
+        }
+    }
+
+    fn _flush(&self) -> Result<(), Error>
+    {
+        match stdout().flush()
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::fromio(e)),
+        }
+    }
+    fn _io(r: std::io::Result<()>) -> Result<(), Error>
+    {
+        match r
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::fromio(e)),
+        }
+    }
+
+    pub fn Clear(&mut self) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0)))?;
+        self._flush()
+    }
+    pub fn ClearLine(&mut self) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), terminal::Clear(terminal::ClearType::CurrentLine), cursor::MoveToColumn(0)))?;
+        self._flush()
+    }
+
+    /// 0 based, x is the column
+    pub fn SetCursor(&mut self, x: u32, y: u32) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), cursor::MoveTo(x.min(u16::MAX as u32) as u16, y.min(u16::MAX as u32) as u16)))?;
+        self._flush()
+    }
+    pub fn ShowCursor(&mut self, show: bool) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        if(show) { CONSOLE::_io(queue!(stdout(), cursor::Show))?; }
+        else     { CONSOLE::_io(queue!(stdout(), cursor::Hide))?; }
+        self.cursor_hidden = !show;
+        self._flush()
+    }
+
+    /// 256 color palette, 0 to 15 are the usual terminal colors
+    pub fn SetForeground(&mut self, color: u32) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), style::SetForegroundColor(Color::AnsiValue(color as u8))))?;
+        self.colored = true;
+        self._flush()
+    }
+    pub fn SetBackground(&mut self, color: u32) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), style::SetBackgroundColor(Color::AnsiValue(color as u8))))?;
+        self.colored = true;
+        self._flush()
+    }
+    pub fn ResetColors(&mut self) -> Result<(), Error>
+    {
+        if(!self.tty) { return Ok(()); }
+        CONSOLE::_io(queue!(stdout(), style::ResetColor))?;
+        self.colored = false;
+        self._flush()
+    }
+
+    /// (columns, rows)
+    pub fn GetSize(&self) -> (u32, u32)
+    {
+        if(!self.tty) { return (CONSOLE_DEFAULT_COLS, CONSOLE_DEFAULT_ROWS); }
+        match terminal::size()
+        {
+            Ok((c, r)) if c > 0 && r > 0 => (c as u32, r as u32),
+            _ => (CONSOLE_DEFAULT_COLS, CONSOLE_DEFAULT_ROWS),
+        }
+    }
+
+    /// second screen for full screen programs, leaving it brings back what was there before
+    pub fn SetAlternateScreen(&mut self, on: bool) -> Result<(), Error>
+    {
+        if(!self.tty || on == self.alternate) { return Ok(()); }
+        if(on) { CONSOLE::_io(queue!(stdout(), terminal::EnterAlternateScreen))?; }
+        else   { CONSOLE::_io(queue!(stdout(), terminal::LeaveAlternateScreen))?; }
+        self.alternate = on;
+        self._flush()
+    }
+
+    /// undoes everything the guest changed, the terminal shouldnt stay changed after the vm is gone
+    pub fn Restore(&mut self)
+    {
+        if(self.colored)       { _ = queue!(stdout(), style::ResetColor); }
+        if(self.cursor_hidden) { _ = queue!(stdout(), cursor::Show); }
+        if(self.alternate)     { _ = queue!(stdout(), terminal::LeaveAlternateScreen); }
+        _ = stdout().flush();
+        self.colored = false;
+        self.cursor_hidden = false;
+        self.alternate = false;
+    }
+
+}
+impl Drop for CONSOLE
+{
+    fn drop(&mut self) { self.Restore(); }
+}
@@ -0,0 +1,122 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const DISK_SECTOR_SIZE: u32 = 512;
+
+#[repr(u8)]
+#[derive(PartialEq)]
+pub enum DiskResult
+{
+    OK = 0,
+    NoDisk,
+    InvalidSector,
+    CouldntRead,
+    CouldntWrite,
+}
+
+/// disk image split into DISK_SECTOR_SIZE sectors, a partial last sector is ignored
+pub struct DISK
+{
+    file: Option<File>,
+    sector_count: u32,
+}
+impl Default for DISK
+{
+    fn default() -> Self { Self::new() }
+}
+impl DISK
+{
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            file: None,
+            sector_count: 0,
+        }
+    }
+
+    pub fn Attach(&mut self, path: &str) -> Result<(), Error>
+    {
+
+        let file = match OpenOptions::new().read(true).write(true).open(path)
+        {
+            Ok(f) => f,
+            Err(e) => return Err(error!("Couldnt open disk image '{}': {}", path, e)),
+        };
+        let len = match file.metadata()
+        {
+            Ok(m) => m.len(),
+            Err(e) => return Err(Error::fromio(e)),
+        };
+
+        self.sector_count = (len / DISK_SECTOR_SIZE as u64).min(u32::MAX as u64) as u32;
+        self.file = Some(file);
+
+        Ok(())
+
+    }
+
+    pub fn GetSectorCount(&self) -> u32 { self.sector_count }
+
+    fn _seek(&mut self, sector: u32) -> Result<&mut File, DiskResult>
+    {
+        let Some(file) = &mut self.file
+        else { return Err(DiskResult::NoDisk); };
+        if(sector >= self.sector_count)
+        {
+            return Err(DiskResult::InvalidSector);
+        }
+        match file.seek(SeekFrom::Start(sector as u64 * DISK_SECTOR_SIZE as u64))
+        {
+            Ok(_) => Ok(file),
+            Err(_) => Err(DiskResult::CouldntRead),
+        }
+    }
+
+    pub fn ReadSector(&mut self, sector: u32) -> (DiskResult, Option<Vec<u8>>)
+    {
+        let file = match self._seek(sector)
+        {
+            Ok(f) => f,
+            Err(r) => return (r, None),
+        };
+        let mut buffer = vec![0; DISK_SECTOR_SIZE as usize];
+        match file.read_exact(&mut buffer)
+        {
+            Ok(_) => (DiskResult::OK, Some(buffer)),
+            Err(_) => (DiskResult::CouldntRead, None),
+        }
+    }
+
+    pub fn WriteSector(&mut self, sector: u32, buffer: Vec<u8>) -> DiskResult
+    {
+        let file = match self._seek(sector)
+        {
+            Ok(f) => f,
+            Err(DiskResult::CouldntRead) => return DiskResult::CouldntWrite,
+            Err(r) => return r,
+        };
+        match file.write_all(&buffer)
+        {
+            Ok(_) => DiskResult::OK,
+            Err(_) => DiskResult::CouldntWrite,
+        }
+    }
+
+    pub fn Flush(&mut self) -> DiskResult
+    {
+        let Some(file) = &mut self.file
+        else { return DiskResult::NoDisk; };
+        match file.sync_data()
+        {
+            Ok(_) => DiskResult::OK,
+            Err(_) => DiskResult::CouldntWrite,
+        }
+    }
+
+}
@@ -0,0 +1,145 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+#[cfg(feature = "terminal")]
+use crossterm::{cursor, queue, style, terminal};
+#[cfg(feature = "terminal")]
+use crossterm::style::Color;
+#[cfg(feature = "terminal")]
+use std::io::{stdout, Write};
+use std::time::{Duration, Instant};
+
+pub const DISPLAY_COLS: u32 = 80;
+pub const DISPLAY_ROWS: u32 = 25;
+/// character byte, then attribute byte [low nibble foreground, high nibble background]
+pub const DISPLAY_SIZE: u32 = DISPLAY_COLS * DISPLAY_ROWS * 2;
+
+const DISPLAY_FRAME_TIME: Duration = Duration::from_millis(33);
+
+// vga color order [black, blue, green, cyan, red, ...] to the terminal palette [black, red, green, yellow, ...]
+#[cfg(feature = "terminal")]
+const VGA_TO_ANSI: [u8; 16] = [0, 4, 2, 6, 1, 5, 3, 7, 8, 12, 10, 14, 9, 13, 11, 15];
+
+/// text mode screen read straight from guest memory
+/// -> drawn to the terminal, or only kept in memory [headless] and written out as text with Dump()
+pub struct DISPLAY
+{
+    pub adr: u32,
+    pub headless: bool,
+    /// cells as they were last drawn
+    last: Vec<u8>,
+    last_draw: Instant,
+}
+impl DISPLAY
+{
+
+    pub fn new(adr: u32, headless: bool) -> Result<Self, Error>
+    {
+        if(adr as u64 + DISPLAY_SIZE as u64 > 0x10000)
+        {
+            return Err(error!("Display at {:#x} would reach past the end of ram! [{:#x} bytes]", adr, DISPLAY_SIZE));
+        }
+        if(!headless && !cfg!(feature = "terminal"))
+        {
+            return Err(error!("Drawing the display needs erebos_vm built with the terminal feature, --display-dump still works!"));
+        }
+        Ok(Self
+        {
+            adr,
+            headless,
+            last: Vec::new(),
+            last_draw: Instant::now(),
+        })
+    }
+
+    /// at most one frame every DISPLAY_FRAME_TIME, unless forced
+    pub fn ShouldDraw(&self) -> bool
+    {
+        !self.headless && self.last_draw.elapsed() >= DISPLAY_FRAME_TIME
+    }
+
+    pub fn Draw(&mut self, cells: Vec<u8>) -> Result<(), Error>
+    {
+
+        self.last_draw = Instant::now();
+        if(self.headless || cells == self.last) { return Ok(()); }
+
+        self._print(&cells)?;
+        self.last = cells;
+
+        Ok(())
+
+    }
+
+    #[cfg(feature = "terminal")]
+    fn _print(&self, cells: &[u8]) -> Result<(), Error>
+    {
+
+        let mut out = stdout();
+        let io = |r: std::io::Result<()>| match r
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::fromio(e)),
+        };
+
+        io(queue!(out, cursor::SavePosition, cursor::Hide))?;
+        for row in 0..DISPLAY_ROWS as usize
+        {
+            io(queue!(out, cursor::MoveTo(0, row as u16)))?;
+            let mut attr: Option<u8> = None;
+            for col in 0..DISPLAY_COLS as usize
+            {
+                let i = (row * DISPLAY_COLS as usize + col) * 2;
+                if(attr != Some(cells[i + 1]))
+                {
+                    let a = cells[i + 1];
+                    io(queue!(out,
+                        style::SetForegroundColor(Color::AnsiValue(VGA_TO_ANSI[(a & 0x0F) as usize])),
+                        style::SetBackgroundColor(Color::AnsiValue(VGA_TO_ANSI[(a >> 4) as usize])),
+                    ))?;
+                    attr = Some(a);
+                }
+                io(queue!(out, style::Print(DISPLAY::_char(cells[i]))))?;
+            }
+        }
+        io(queue!(out, style::ResetColor, cursor::RestorePosition, cursor::Show))?;
+        io(out.flush())
+
+    }
+    #[cfg(not(feature = "terminal"))]
+    fn _print(&self, _cells: &[u8]) -> Result<(), Error> { Ok(()) }
+
+    /// so the first frame doesnt mix with whatever was on the terminal
+    #[cfg(feature = "terminal")]
+    pub fn Clear(&mut self) -> Result<(), Error>
+    {
+        if(self.headless) { return Ok(()); }
+        match queue!(stdout(), terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, DISPLAY_ROWS as u16)).and_then(|_| stdout().flush())
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::fromio(e)),
+        }
+    }
+    #[cfg(not(feature = "terminal"))]
+    pub fn Clear(&mut self) -> Result<(), Error> { Ok(()) }
+
+    fn _char(c: u8) -> char
+    {
+        if(c.is_ascii_graphic()) { c as char } else { ' ' }
+    }
+
+    /// one line per row, trailing spaces cut off, attributes are left out
+    pub fn Text(cells: &[u8]) -> String
+    {
+        let mut out = String::new();
+        for row in cells.chunks(DISPLAY_COLS as usize * 2)
+        {
+            let line: String = row.chunks(2).map(|c| DISPLAY::_char(c[0])).collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+}
@@ -1,11 +1,13 @@
 use erebos::instructions::*;
 use erebos::error;
+use crate::memfs::MEMFS;
 use std::collections::HashMap;
-use std::fs::{DirEntry, File};
-use std::io::{BufReader, BufWriter, Write};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::io::Read;
-use std::io::Seek;
-use std::path::Path;
+use std::io::{Seek, SeekFrom};
+use std::io::Result as IOResult;
+use std::path::{Component, Path, PathBuf};
 
 macro_rules! fs_error
 {
@@ -29,6 +31,11 @@ pub enum FSResult
     IsntDir,
     IsntFile,
     NotFound,
+    AccessDenied,
+    ReadOnly,
+    InvalidHandle,
+    TooManyHandles,
+    InvalidArgument,
     Unknown
 }
 impl From<u8> for FSResult
@@ -53,12 +60,47 @@ impl From<FSResult> for u8
     }
 }
 
+// Open() mode bits
+pub const FS_OPEN_READ    : u32 = 0x01;
+pub const FS_OPEN_WRITE   : u32 = 0x02;
+pub const FS_OPEN_CREATE  : u32 = 0x04;
+pub const FS_OPEN_TRUNCATE: u32 = 0x08;
+
+// entry types of Stat(), ListDir() and GetEntryType()
+pub const FS_TYPE_NONE: u32 = 0x00;
+pub const FS_TYPE_FILE: u32 = 0x01;
+pub const FS_TYPE_DIR : u32 = 0x02;
+
+const FS_MAX_HANDLES: usize = 64;
+
+pub enum HandleFile
+{
+    Host(File),
+    /// follows the path, so a handle to a renamed or deleted file just fails
+    Memory { path: PathBuf, pos: u64 },
+}
+pub struct FileHandle
+{
+    file: HandleFile,
+    write: bool,
+}
+
 pub struct FS
 {
     pub active: bool,
     pub file_loc: String,
-    pub map: HashMap<u8, DirEntry>,
-    pub file_count: u8,
+    pub map: HashMap<u32, PathBuf>,
+    pub file_count: u32,
+    /// every guest path resolves inside this directory [canonical], the cwd unless --fs-root is given
+    pub sandbox: PathBuf,
+    pub read_only: bool,
+    /// where relative paths start, always inside the sandbox
+    pub cwd: PathBuf,
+    /// open files by descriptor, descriptors dont change on reindex and arent reused right away
+    pub handles: HashMap<u32, FileHandle>,
+    pub next_handle: u32,
+    /// if set everything happens in here instead of on the host [--fs-memory]
+    pub memory: Option<MEMFS>,
 }
 impl Default for FS
 {
@@ -69,14 +111,213 @@ impl FS
 
     pub fn new() -> Self
     {
+        let sandbox = std::env::current_dir().and_then(|d| d.canonicalize()).unwrap_or_else(|_| PathBuf::from("."));
         Self
         {
             active: false,
             file_loc: String::new(),
             map: HashMap::new(),
             file_count: 0,
+            cwd: sandbox.clone(),
+            sandbox,
+            read_only: false,
+            handles: HashMap::new(),
+            next_handle: 1,
+            memory: None,
+        }
+    }
+
+    pub fn SetSandbox(&mut self, path: &str) -> Result<(), Error>
+    {
+        let p = match Path::new(path).canonicalize()
+        {
+            Ok(p) => p,
+            Err(e) => return Err(fs_error!("Root '{}' is not usable: {}", path, e)),
+        };
+        if(!p.is_dir())
+        {
+            return Err(fs_error!("Root '{}' is not a directory!", path));
+        }
+        self.sandbox = p.clone();
+        self.cwd = p;
+        Ok(())
+    }
+    /// swaps the host for an in memory tree, its / becomes the sandbox
+    pub fn UseMemory(&mut self, memory: MEMFS)
+    {
+        self.sandbox = PathBuf::from("/");
+        self.cwd = self.sandbox.clone();
+        self.memory = Some(memory);
+    }
+    pub fn DumpMemory(&self, path: &str) -> Result<(), Error>
+    {
+        match &self.memory
+        {
+            Some(m) => m.dump_tar(path),
+            None => Err(fs_error!("Not running in memory, nothing to dump!")),
+        }
+    }
+
+    /// maps a guest path into the sandbox, absolute paths start at the sandbox root, relative ones at the cwd
+    /// -> None if it would end up outside, through .. or a symlink
+    fn _confine(&self, path: &str) -> Option<PathBuf>
+    {
+
+        let mut out = if(path.starts_with('/') || path.starts_with('\\'))
+        {
+            self.sandbox.clone()
+        }
+        else
+        {
+            self.cwd.clone()
+        };
+
+        for c in Path::new(path).components()
+        {
+            match c
+            {
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {},
+                Component::ParentDir =>
+                {
+                    if(out == self.sandbox) { return None; }
+                    out.pop();
+                },
+                Component::Normal(n) => out.push(n),
+            }
+        }
+
+        // no symlinks in memory
+        if(self.memory.is_some()) { return Some(out); }
+
+        // every symlink on the way has to stay inside once it is followed,
+        // a dangling one doesnt resolve at all and creating through it could land anywhere
+        let mut part = self.sandbox.clone();
+        for c in out.strip_prefix(&self.sandbox).ok()?.components()
+        {
+            part.push(c);
+            match part.symlink_metadata()
+            {
+                Ok(m) if m.file_type().is_symlink() =>
+                {
+                    match part.canonicalize()
+                    {
+                        Ok(real) if real.starts_with(&self.sandbox) => {},
+                        _ => return None,
+                    }
+                },
+                Ok(_) => {},
+                // nothing below a missing part exists either
+                Err(_) => break,
+            }
+        }
+        Some(out)
+
+    }
+    fn _check_index_file(&self, index: u32) -> FSResult
+    {
+        let r = self._check_index(index);
+        if(r != FSResult::OK) { return r; }
+        match self.map.get(&index).map(|p| self._is_file(p))
+        {
+            Some(true) => FSResult::OK,
+            _ => FSResult::IsntFile,
         }
     }
+    fn _check_writable(&self) -> FSResult
+    {
+        if(self.read_only) { FSResult::ReadOnly } else { FSResult::OK }
+    }
+
+    // everything below goes to the memory tree if there is one, to the host otherwise
+
+    fn _exists(&self, p: &Path) -> bool
+    {
+        match &self.memory { Some(m) => m.exists(p), None => p.exists() }
+    }
+    fn _is_dir(&self, p: &Path) -> bool
+    {
+        match &self.memory { Some(m) => m.is_dir(p), None => p.is_dir() }
+    }
+    fn _is_file(&self, p: &Path) -> bool
+    {
+        match &self.memory { Some(m) => m.is_file(p), None => p.is_file() }
+    }
+    fn _entry_type(&self, p: &Path) -> u32
+    {
+        if(self._is_dir(p)) { FS_TYPE_DIR }
+        else if(self._is_file(p)) { FS_TYPE_FILE }
+        else { FS_TYPE_NONE }
+    }
+    fn _len(&self, p: &Path) -> IOResult<u64>
+    {
+        match &self.memory { Some(m) => m.len(p), None => std::fs::metadata(p).map(|m| m.len()) }
+    }
+    /// unix seconds, always 0 in memory
+    fn _modified(&self, p: &Path) -> u32
+    {
+        if(self.memory.is_some()) { return 0; }
+        std::fs::metadata(p).and_then(|m| m.modified()).ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0)
+    }
+    fn _read(&self, p: &Path) -> IOResult<Vec<u8>>
+    {
+        match &self.memory { Some(m) => m.read(p), None => std::fs::read(p) }
+    }
+    fn _read_at(&self, p: &Path, pos: u64, n: u64) -> IOResult<Vec<u8>>
+    {
+        if let Some(m) = &self.memory { return m.read_at(p, pos, n); }
+        let mut file = File::open(p)?;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut buffer = Vec::new();
+        file.take(n).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+    fn _write(&mut self, p: &Path, data: &[u8]) -> IOResult<()>
+    {
+        match &mut self.memory { Some(m) => m.write(p, data), None => std::fs::write(p, data) }
+    }
+    fn _write_at(&mut self, p: &Path, pos: u64, data: &[u8]) -> IOResult<()>
+    {
+        if let Some(m) = &mut self.memory { return m.write_at(p, pos, data); }
+        let mut file = OpenOptions::new().write(true).open(p)?;
+        file.seek(SeekFrom::Start(pos))?;
+        file.write_all(data)
+    }
+    fn _set_len(&mut self, p: &Path, len: u64) -> IOResult<()>
+    {
+        if let Some(m) = &mut self.memory { return m.set_len(p, len); }
+        OpenOptions::new().write(true).open(p)?.set_len(len)
+    }
+    fn _create_dir(&mut self, p: &Path) -> IOResult<()>
+    {
+        match &mut self.memory { Some(m) => m.create_dir(p), None => std::fs::create_dir(p) }
+    }
+    fn _remove_dir(&mut self, p: &Path) -> IOResult<()>
+    {
+        match &mut self.memory { Some(m) => m.remove_dir(p), None => std::fs::remove_dir(p) }
+    }
+    fn _remove_file(&mut self, p: &Path) -> IOResult<()>
+    {
+        match &mut self.memory { Some(m) => m.remove_file(p), None => std::fs::remove_file(p) }
+    }
+    fn _rename(&mut self, from: &Path, to: &Path) -> IOResult<()>
+    {
+        match &mut self.memory { Some(m) => m.rename(from, to), None => std::fs::rename(from, to) }
+    }
+    /// files and directories only, unsorted
+    fn _list(&self, p: &Path) -> IOResult<Vec<PathBuf>>
+    {
+        if let Some(m) = &self.memory { return m.list(p); }
+        let mut out = Vec::new();
+        for entry in std::fs::read_dir(p)?
+        {
+            let path = entry?.path();
+            if(path.is_file() || path.is_dir()) { out.push(path); }
+        }
+        Ok(out)
+    }
 
     pub fn IsActive(&self) -> bool { self.active }
 
@@ -85,7 +326,7 @@ impl FS
         if(!self.IsActive()) { return Err(fs_error!("Is not active!")); }
         Ok(())
     }
-    fn _check_index(&self, index: u8) -> FSResult
+    fn _check_index(&self, index: u32) -> FSResult
     {
         if(index == 0 || index > self.file_count)
         {
@@ -106,41 +347,31 @@ impl FS
 
         self.map.clear();
 
-        if(!matches!(std::fs::exists(&self.file_loc), Ok(true)))
+        let loc = PathBuf::from(&self.file_loc);
+
+        if(!self._exists(&loc))
         {
             return Err(fs_error!("File location '{}' does not exist!", self.file_loc));
         }
 
-        if(!Path::new(&self.file_loc).is_dir())
+        if(!self._is_dir(&loc))
         {
             return Err(fs_error!("File location '{}' is not a directory!", self.file_loc));
         }
 
-        let mut index: u8 = 0;
-        for entry in match std::fs::read_dir(&self.file_loc)
-            {
-                Ok(d) => d,
-                Err(e) => return Err(Error::fromio(e)),
-            }
+        let mut index: u32 = 0;
+        let mut entries = match self._list(&loc)
         {
+            Ok(e) => e,
+            Err(e) => return Err(Error::fromio(e)),
+        };
 
-            let entry = match entry
-            {
-                Ok(d) => d,
-                Err(e) => return Err(Error::fromio(e)),
-            };
-
-            let meta = match entry.metadata()
-            {
-                Ok(d) => d,
-                Err(e) => return Err(Error::fromio(e)),
-            };
-
-            if(!meta.is_file()) { continue; }
-
+        // read_dir has no fixed order, indices should not depend on the host
+        entries.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        for entry in entries
+        {
             index += 1;
             self.map.insert(index, entry);
-
         }
 
         self.file_count = index;
@@ -148,25 +379,26 @@ impl FS
         Ok(())
 
     }
-    pub fn GetFiles(&self) -> Result<u8, Error>
+    pub fn GetFiles(&self) -> Result<u32, Error>
     {
         self._check_active()?;
         Ok(self.file_count)
     }
 
-    pub fn CreateFile(&self, path: String) -> Result<FSResult, Error>
+    pub fn CreateFile(&mut self, path: String) -> Result<FSResult, Error>
     {
 
         self._check_active()?;
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(FSResult::AccessDenied); };
         
-        if( match std::fs::exists(&path)
-            {
-                Ok(b) => b,
-                Err(e) => return Err(Error::fromio(e)),
-            } ) 
-        { return Ok(FSResult::FileExists); }
+        if(self._exists(&path)) { return Ok(FSResult::FileExists); }
 
-        if let Err(e) = std::fs::write(path, vec![])
+        if let Err(e) = self._write(&path, &[])
         {
             return Err(Error::fromio(e));
         }
@@ -174,28 +406,29 @@ impl FS
         Ok(FSResult::OK)
 
     }
-    pub fn DeleteFile(&mut self, index: u8) -> Result<FSResult, Error>
+    pub fn DeleteFile(&mut self, index: u32) -> Result<FSResult, Error>
     {
         
         self._check_active()?;
 
-        let r = self._check_index(index);
+        let r = self._check_writable();
         if(r != FSResult::OK) { return Ok(r); }
 
-        let entry = match self.map.get(&index)
+        let r = self._check_index_file(index);
+        if(r != FSResult::OK) { return Ok(r); }
+
+        let path = match self.map.get(&index)
         {
-            Some(e) => e,
+            Some(p) => p.clone(),
             None => unreachable!(),
         };
 
-        let path = entry.path();
-
-        if(!path.exists())
+        if(!self._exists(&path))
         {
             return Err(fs_error!("DeleteFile(): index: {}, file: {} : File doesnt exist!", index, path.display()));
         }
 
-        match std::fs::remove_file(path)
+        match self._remove_file(&path)
         {
             Ok(_) => {},
             Err(e) => return Err(Error::fromio(e)),
@@ -210,13 +443,10 @@ impl FS
     pub fn FileExists(&self, path: String) -> Result<u8, Error>
     {
 
-        let b = match std::fs::exists(&path)
-        {
-            Ok(b) => b,
-            Err(e) => return Err(Error::fromio(e)),
-        };
+        let Some(path) = self._confine(&path)
+        else { return Ok(0); };
 
-        if(b) { Ok(1) }
+        if(self._exists(&path)) { Ok(1) }
         else  { Ok(0) }
 
     }
@@ -224,9 +454,10 @@ impl FS
     pub fn GetSupDir(&self, path: String) -> Result<(FSResult, Option<String>), Error>
     {
 
-        if let Err(e) = std::fs::exists(&path)
+        // the answer stays a guest path, only checked against the sandbox
+        if(self._confine(&path).and_then(|p| p.parent().map(|p| p.starts_with(&self.sandbox))) != Some(true))
         {
-            return Err(Error::fromio(e));
+            return Ok(( FSResult::AccessDenied, None ));
         }
 
         let p = Path::new(&path);
@@ -242,19 +473,21 @@ impl FS
     pub fn QuickRead(&self, path: String) -> Result<(FSResult, Option<Vec<u8>>), Error>
     {
 
-        let path = Path::new(&path);
+        let Some(path) = self._confine(&path)
+        else { return Ok(( FSResult::AccessDenied, None )); };
+        let path = path.as_path();
 
-        if(!path.exists())
+        if(!self._exists(path))
         {
             return Ok(( FSResult::FileDoesntExist, None ));
         }
 
-        if(!path.is_file())
+        if(!self._is_file(path))
         {
             return Ok(( FSResult::IsntFile, None ));
         }
 
-        match std::fs::read(path)
+        match self._read(path)
         {
             Ok(v) => Ok(( FSResult::OK, Some(v) )),
             Err(e) => Err(Error::fromio(e))
@@ -265,21 +498,20 @@ impl FS
     pub fn SetRoot(&mut self, path: String) -> Result<FSResult, Error>
     {
 
-        if(!match std::fs::exists(&path)
-        { 
-            Ok(b) => b, 
-            Err(e) => return Err(Error::fromio(e)),
-        })
+        let Some(path) = self._confine(&path)
+        else { return Ok(FSResult::AccessDenied); };
+
+        if(!self._exists(&path))
         {
             return Ok(FSResult::RootDoesntExist);
         }
-        if(!Path::new(&path).is_dir())
+        if(!self._is_dir(&path))
         {
             return Ok(FSResult::IsntDir);
         }
 
         self.active = true;
-        self.file_loc = path;
+        self.file_loc = path.to_string_lossy().to_string();
 
         Ok(FSResult::OK)
 
@@ -287,7 +519,7 @@ impl FS
 
 
 
-    pub fn GetFileName(&self, index: u8) -> Result<(FSResult, Option<String>), Error>
+    pub fn GetFileName(&self, index: u32) -> Result<(FSResult, Option<String>), Error>
     {
 
         self._check_active()?;
@@ -301,16 +533,17 @@ impl FS
             None => unreachable!(),
         };
 
-        let filename = match entry.file_name().into_string()
+        let name = entry.file_name().unwrap_or_default();
+        let filename = match name.to_str()
         {
-            Ok(s) => s,
-            Err(_) => return Err(fs_error!("Could not convert OSString '{:?}' into String!", entry.file_name())),
+            Some(s) => s.to_string(),
+            None => return Err(fs_error!("Could not convert OSString '{:?}' into String!", name)),
         };
 
         Ok(( FSResult::OK, Some(filename) ))
 
     }
-    pub fn SetFileName(&mut self, index: u8, name: String) -> Result<FSResult, Error>
+    pub fn SetFileName(&mut self, index: u32, name: String) -> Result<FSResult, Error>
     {
 
         self._check_active()?;
@@ -318,17 +551,25 @@ impl FS
         let r = self._check_index(index);
         if(r != FSResult::OK) { return Ok(r); }
 
-        let entry = match self.map.get(&index)
+        let oldpath = match self.map.get(&index)
         {
-            Some(e) => e,
+            Some(p) => p.clone(),
             None => unreachable!(),
         };
 
-        let     oldpath = entry.path();
-        let mut newpath = entry.path();
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
+
+        // the new name can not move the file, not even inside the sandbox
+        if(name.is_empty() || !matches!(Path::new(&name).components().collect::<Vec<_>>().as_slice(), [Component::Normal(_)]))
+        {
+            return Ok(FSResult::AccessDenied);
+        }
+
+        let mut newpath = oldpath.clone();
         newpath.set_file_name(name);
 
-        if let Err(e) = std::fs::rename(oldpath, newpath)
+        if let Err(e) = self._rename(&oldpath, &newpath)
         {
             return Err(Error::fromio(e));
         };
@@ -339,12 +580,12 @@ impl FS
 
     }
 
-    pub fn GetFileLength(&self, index: u8) -> Result<(FSResult, Option<u32>), Error>
+    pub fn GetFileLength(&self, index: u32) -> Result<(FSResult, Option<u32>), Error>
     {
 
         self._check_active()?;
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok((r, None)); }
 
         let entry = match self.map.get(&index)
@@ -353,23 +594,24 @@ impl FS
             None => unreachable!(),
         };
 
-        let meta = match entry.metadata()
+        let size = match self._len(entry)
         {
-            Ok(m) => m,
+            Ok(l) => l as u32,
             Err(e) => return Err(Error::fromio(e)),
         };
 
-        let size = meta.len() as u32;
-
         Ok(( FSResult::OK, Some(size) ))
 
     }
-    pub fn SetFileLength(&self, index: u8, length: u32) -> Result<FSResult, Error>
+    pub fn SetFileLength(&mut self, index: u32, length: u32) -> Result<FSResult, Error>
     {
 
         self._check_active()?;
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok(r); }
 
         let entry = match self.map.get(&index)
@@ -378,13 +620,8 @@ impl FS
             None => unreachable!(),
         };
 
-        let file = match File::open(entry.path())
-        {
-            Ok(f) => f,
-            Err(e) => return Err(Error::fromio(e)),
-        };
-
-        if let Err(e) = file.set_len(length as u64)
+        let path = entry.clone();
+        if let Err(e) = self._set_len(&path, length as u64)
         {
             return Err(Error::fromio(e));
         };
@@ -393,12 +630,12 @@ impl FS
 
     }
 
-    pub fn ReadFile(&self, index: u8) -> Result<(FSResult, Option<Vec<u8>>), Error>
+    pub fn ReadFile(&self, index: u32) -> Result<(FSResult, Option<Vec<u8>>), Error>
     {
 
         self._check_active()?;
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok((r, None)); }
 
         let entry = match self.map.get(&index)
@@ -407,7 +644,7 @@ impl FS
             None => unreachable!(),
         };
 
-        let buffer = match std::fs::read(entry.path())
+        let buffer = match self._read(entry)
         {
             Ok(b) => b,
             Err(e) => return Err(Error::fromio(e)),
@@ -416,12 +653,12 @@ impl FS
         Ok(( FSResult::OK, Some(buffer) ))
 
     }
-    pub fn ReadFileAt(&self, index: u8, pos: u32) -> Result<(FSResult, Option<u8>), Error>
+    pub fn ReadFileAt(&self, index: u32, pos: u32) -> Result<(FSResult, Option<u8>), Error>
     {
 
         self._check_active()?;
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok((r, None)); }
 
         let entry = match self.map.get(&index)
@@ -430,36 +667,24 @@ impl FS
             None => unreachable!(),
         };
 
-        let file = match File::open(entry.path())
-        {
-            Ok(f) => f,
-            Err(e) => return Err(Error::fromio(e)),
-        };
-
-        let mut buffer: [u8; 1] = [0; 1];
-
-        let mut reader = BufReader::new(file);
-        if let Err(e) = reader.seek(std::io::SeekFrom::Start(pos as u64))
+        match self._read_at(entry, pos as u64, 1)
         {
-            return Err(Error::fromio(e));
+            Ok(b) if b.is_empty() => Ok(( FSResult::CouldntRead, None )),
+            Ok(b) => Ok(( FSResult::OK, Some(b[0]) )),
+            Err(e) => Err(Error::fromio(e)),
         }
-        match reader.read(&mut buffer)
-        {
-            Ok(0) => return Ok(( FSResult::CouldntRead, None )),
-            Err(e) => return Err(Error::fromio(e)),
-            _ => {},
-        };
-
-        Ok(( FSResult::OK, Some(buffer[0]) ))
 
     }
 
-    pub fn WriteFile(&self, index: u8, buffer: Vec<u8>) -> Result<FSResult, Error>
+    pub fn WriteFile(&mut self, index: u32, buffer: Vec<u8>) -> Result<FSResult, Error>
     {
 
         self._check_active()?;
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok(r); }
 
         let entry = match self.map.get(&index)
@@ -468,14 +693,8 @@ impl FS
             None => unreachable!(),
         };
 
-        let file = match File::open(entry.path())
-        {
-            Ok(f) => f,
-            Err(e) => return Err(Error::fromio(e)),
-        };
-
-        let mut writer = BufWriter::new(file);
-        if let Err(e) = writer.write(&buffer)
+        let path = entry.clone();
+        if let Err(e) = self._write(&path, &buffer)
         {
             return Err(Error::fromio(e));
         };
@@ -483,12 +702,15 @@ impl FS
         Ok(FSResult::OK)
 
     }
-    pub fn WriteFileAt(&self, index: u8, pos: u32, val: u8) -> Result<FSResult, Error>
+    pub fn WriteFileAt(&mut self, index: u32, pos: u32, val: u8) -> Result<FSResult, Error>
     {
 
         self._check_active()?;
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
         
-        let r = self._check_index(index);
+        let r = self._check_index_file(index);
         if(r != FSResult::OK) { return Ok(r); }
 
         let entry = match self.map.get(&index)
@@ -497,27 +719,441 @@ impl FS
             None => unreachable!(),
         };
 
-        let file = match File::open(entry.path())
+        let path = entry.clone();
+        if let Err(e) = self._write_at(&path, pos as u64, &[val])
         {
-            Ok(f) => f,
-            Err(e) => return Err(Error::fromio(e)),
+            return Err(Error::fromio(e));
         };
 
-        let buffer: [u8; 1] = [val; 1];
-        let mut writer = BufWriter::new(file);
-        if let Err(e) = writer.seek(std::io::SeekFrom::Start(pos as u64))
+        Ok(FSResult::OK)
+
+    }
+
+
+
+
+    pub fn Open(&mut self, path: String, mode: u32) -> Result<(FSResult, Option<u32>), Error>
+    {
+
+        let write = mode & (FS_OPEN_WRITE | FS_OPEN_CREATE | FS_OPEN_TRUNCATE) != 0;
+
+        if(write)
         {
-            return Err(Error::fromio(e));
+            let r = self._check_writable();
+            if(r != FSResult::OK) { return Ok((r, None)); }
+        }
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(( FSResult::AccessDenied, None )); };
+
+        if(!self._exists(&path) && mode & FS_OPEN_CREATE == 0)
+        {
+            return Ok(( FSResult::FileDoesntExist, None ));
         }
-        match writer.write(&buffer)
+        if(self._exists(&path) && !self._is_file(&path))
         {
-            Ok(0) => return Ok(FSResult::CouldntWrite),
-            Err(e) => return Err(Error::fromio(e)),
-            _ => {},
+            return Ok(( FSResult::IsntFile, None ));
+        }
+        if(self.handles.len() >= FS_MAX_HANDLES)
+        {
+            return Ok(( FSResult::TooManyHandles, None ));
+        }
+
+        let file = if let Some(m) = &mut self.memory
+        {
+            let created = if(!m.is_file(&path)) { m.write(&path, &[]) } else { Ok(()) };
+            let truncated = if(mode & FS_OPEN_TRUNCATE != 0) { m.set_len(&path, 0) } else { Ok(()) };
+            if(created.is_err() || truncated.is_err())
+            {
+                return Ok(( FSResult::CouldntWrite, None ));
+            }
+            HandleFile::Memory { path, pos: 0 }
+        }
+        else
+        {
+            match OpenOptions::new()
+                .read(mode & FS_OPEN_READ != 0 || !write)
+                .write(write)
+                .create(mode & FS_OPEN_CREATE != 0)
+                .truncate(mode & FS_OPEN_TRUNCATE != 0)
+                .open(&path)
+            {
+                Ok(f) => HandleFile::Host(f),
+                Err(_) => return Ok(( if(write) { FSResult::CouldntWrite } else { FSResult::CouldntRead }, None )),
+            }
+        };
+
+        while(self.next_handle == 0 || self.handles.contains_key(&self.next_handle))
+        {
+            self.next_handle = self.next_handle.wrapping_add(1);
+        }
+        let fd = self.next_handle;
+        self.next_handle = self.next_handle.wrapping_add(1);
+
+        self.handles.insert(fd, FileHandle { file, write });
+
+        Ok(( FSResult::OK, Some(fd) ))
+
+    }
+    pub fn Close(&mut self, fd: u32) -> Result<FSResult, Error>
+    {
+        match self.handles.remove(&fd)
+        {
+            Some(_) => Ok(FSResult::OK),
+            None => Ok(FSResult::InvalidHandle),
+        }
+    }
+
+    pub fn Read(&mut self, fd: u32, n: u32) -> Result<(FSResult, Option<Vec<u8>>), Error>
+    {
+
+        let Some(h) = self.handles.get_mut(&fd)
+        else { return Ok(( FSResult::InvalidHandle, None )); };
+
+        let read = match (&mut h.file, &self.memory)
+        {
+            (HandleFile::Host(file), _) =>
+            {
+                let mut buffer: Vec<u8> = Vec::new();
+                Read::by_ref(file).take(n as u64).read_to_end(&mut buffer).map(|_| buffer)
+            },
+            (HandleFile::Memory { path, pos }, Some(m)) =>
+            {
+                let buffer = m.read_at(path, *pos, n as u64);
+                if let Ok(b) = &buffer { *pos += b.len() as u64; }
+                buffer
+            },
+            (HandleFile::Memory { .. }, None) => unreachable!(),
+        };
+
+        match read
+        {
+            Ok(buffer) => Ok(( FSResult::OK, Some(buffer) )),
+            Err(_) => Ok(( FSResult::CouldntRead, None )),
+        }
+
+    }
+    pub fn Write(&mut self, fd: u32, buffer: Vec<u8>) -> Result<(FSResult, Option<u32>), Error>
+    {
+
+        let Some(h) = self.handles.get_mut(&fd)
+        else { return Ok(( FSResult::InvalidHandle, None )); };
+
+        if(!h.write)
+        {
+            return Ok(( FSResult::ReadOnly, None ));
+        }
+
+        let written = match (&mut h.file, &mut self.memory)
+        {
+            (HandleFile::Host(file), _) => file.write_all(&buffer),
+            (HandleFile::Memory { path, pos }, Some(m)) =>
+            {
+                let r = m.write_at(path, *pos, &buffer);
+                if(r.is_ok()) { *pos += buffer.len() as u64; }
+                r
+            },
+            (HandleFile::Memory { .. }, None) => unreachable!(),
+        };
+
+        match written
+        {
+            Ok(_) => Ok(( FSResult::OK, Some(buffer.len() as u32) )),
+            Err(_) => Ok(( FSResult::CouldntWrite, None )),
+        }
+
+    }
+
+    /// whence: 0 from the start, 1 from the current position, 2 from the end
+    pub fn Seek(&mut self, fd: u32, offset: i32, whence: u32) -> Result<(FSResult, Option<u32>), Error>
+    {
+
+        let Some(h) = self.handles.get_mut(&fd)
+        else { return Ok(( FSResult::InvalidHandle, None )); };
+
+        let from = match whence
+        {
+            0 if offset < 0 => return Ok(( FSResult::InvalidArgument, None )),
+            0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset as i64),
+            2 => SeekFrom::End(offset as i64),
+            _ => return Ok(( FSResult::InvalidArgument, None )),
+        };
+
+        let moved = match (&mut h.file, &self.memory)
+        {
+            (HandleFile::Host(file), _) => file.seek(from),
+            (HandleFile::Memory { path, pos }, Some(m)) =>
+            {
+                let new = match from
+                {
+                    SeekFrom::Start(o) => Some(o),
+                    SeekFrom::Current(o) => pos.checked_add_signed(o),
+                    SeekFrom::End(o) => m.len(path).ok().and_then(|l| l.checked_add_signed(o)),
+                };
+                match new
+                {
+                    Some(p) => { *pos = p; Ok(p) },
+                    None => Err(std::io::ErrorKind::InvalidInput.into()),
+                }
+            },
+            (HandleFile::Memory { .. }, None) => unreachable!(),
         };
 
+        match moved
+        {
+            Ok(p) => Ok(( FSResult::OK, Some(p as u32) )),
+            // in front of the start
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => Ok(( FSResult::InvalidArgument, None )),
+            Err(_) => Ok(( FSResult::CouldntRead, None )),
+        }
+
+    }
+    pub fn Tell(&mut self, fd: u32) -> Result<(FSResult, Option<u32>), Error>
+    {
+        self.Seek(fd, 0, 1)
+    }
+    pub fn Truncate(&mut self, fd: u32, length: u32) -> Result<FSResult, Error>
+    {
+
+        let Some(h) = self.handles.get_mut(&fd)
+        else { return Ok(FSResult::InvalidHandle); };
+
+        if(!h.write)
+        {
+            return Ok(FSResult::ReadOnly);
+        }
+
+        let truncated = match (&h.file, &mut self.memory)
+        {
+            (HandleFile::Host(file), _) => file.set_len(length as u64),
+            (HandleFile::Memory { path, .. }, Some(m)) => m.set_len(path, length as u64),
+            (HandleFile::Memory { .. }, None) => unreachable!(),
+        };
+
+        match truncated
+        {
+            Ok(_) => Ok(FSResult::OK),
+            Err(_) => Ok(FSResult::CouldntWrite),
+        }
+
+    }
+
+
+
+    pub fn ChangeDir(&mut self, path: String) -> Result<FSResult, Error>
+    {
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(FSResult::AccessDenied); };
+
+        if(!self._exists(&path))
+        {
+            return Ok(FSResult::FileDoesntExist);
+        }
+        if(!self._is_dir(&path))
+        {
+            return Ok(FSResult::IsntDir);
+        }
+
+        self.cwd = path;
+
         Ok(FSResult::OK)
 
     }
+    /// cwd as the guest sees it, starting with / at the sandbox root
+    pub fn GetCurrentDir(&self) -> String
+    {
+        let mut out = String::new();
+        if let Ok(rel) = self.cwd.strip_prefix(&self.sandbox)
+        {
+            for c in rel.components()
+            {
+                out.push('/');
+                out.push_str(&c.as_os_str().to_string_lossy());
+            }
+        }
+        if(out.is_empty()) { out.push('/'); }
+        out
+    }
+
+    pub fn MakeDir(&mut self, path: String) -> Result<FSResult, Error>
+    {
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(FSResult::AccessDenied); };
+
+        if(self._exists(&path))
+        {
+            return Ok(FSResult::FileExists);
+        }
+
+        match self._create_dir(&path)
+        {
+            Ok(_) => Ok(FSResult::OK),
+            Err(_) => Ok(FSResult::CouldntWrite),
+        }
+
+    }
+    /// only removes empty directories
+    pub fn RemoveDir(&mut self, path: String) -> Result<FSResult, Error>
+    {
+
+        let r = self._check_writable();
+        if(r != FSResult::OK) { return Ok(r); }
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(FSResult::AccessDenied); };
+
+        if(!self._exists(&path))
+        {
+            return Ok(FSResult::FileDoesntExist);
+        }
+        if(!self._is_dir(&path))
+        {
+            return Ok(FSResult::IsntDir);
+        }
+        // the root and the cwd stay
+        if(path == self.sandbox || self.cwd.starts_with(&path))
+        {
+            return Ok(FSResult::AccessDenied);
+        }
+
+        match self._remove_dir(&path)
+        {
+            Ok(_) => Ok(FSResult::OK),
+            Err(_) => Ok(FSResult::CouldntWrite),
+        }
+
+    }
+
+    /// (type, size, modification time in unix seconds)
+    pub fn Stat(&self, path: String) -> Result<(FSResult, Option<(u32, u32, u32)>), Error>
+    {
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(( FSResult::AccessDenied, None )); };
+
+        let len = match self._len(&path)
+        {
+            Ok(l) => l as u32,
+            Err(_) => return Ok(( FSResult::FileDoesntExist, None )),
+        };
+
+        Ok(( FSResult::OK, Some(( self._entry_type(&path), len, self._modified(&path) )) ))
+
+    }
+
+    /// (type, name) of everything in the directory, sorted by name
+    pub fn ListDir(&self, path: String) -> Result<(FSResult, Option<Vec<(u32, String)>>), Error>
+    {
+
+        let Some(path) = self._confine(&path)
+        else { return Ok(( FSResult::AccessDenied, None )); };
+
+        if(!self._is_dir(&path))
+        {
+            return Ok(( if(self._exists(&path)) { FSResult::IsntDir } else { FSResult::FileDoesntExist }, None ));
+        }
+
+        let dir = match self._list(&path)
+        {
+            Ok(d) => d,
+            Err(_) => return Ok(( FSResult::CouldntRead, None )),
+        };
+
+        let mut entries: Vec<(u32, String)> = Vec::new();
+        for entry in dir
+        {
+            let t = self._entry_type(&entry);
+            if(t == FS_TYPE_NONE) { continue; }
+            entries.push(( t, entry.file_name().unwrap_or_default().to_string_lossy().to_string() ));
+        }
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+
+        Ok(( FSResult::OK, Some(entries) ))
+
+    }
+
+    pub fn GetEntryType(&self, index: u32) -> Result<(FSResult, Option<u32>), Error>
+    {
+
+        self._check_active()?;
+
+        let r = self._check_index(index);
+        if(r != FSResult::OK) { return Ok((r, None)); }
+
+        let entry = match self.map.get(&index)
+        {
+            Some(e) => e,
+            None => unreachable!(),
+        };
+
+        Ok(( FSResult::OK, Some(self._entry_type(entry)) ))
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// a fresh sandbox with a directory a and a file outside of it
+    fn sandbox(name: &str) -> (FS, PathBuf, PathBuf)
+    {
+        let base = std::env::temp_dir().join(format!("erebos_confine_{}_{}", name, std::process::id()));
+        _ = std::fs::remove_dir_all(&base);
+        let root = base.join("root");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let mut fs = FS::new();
+        fs.SetSandbox(root.to_str().unwrap()).unwrap();
+        let root = fs.sandbox.clone();
+        (fs, root, outside)
+    }
+
+    #[test]
+    fn parent_dirs_stay_inside()
+    {
+        let (fs, root, _) = sandbox("parent");
+        assert_eq!(fs._confine("a/../b"), Some(root.join("b")));
+        assert_eq!(fs._confine("a/./b"), Some(root.join("a/b")));
+        assert_eq!(fs._confine(".."), None);
+        assert_eq!(fs._confine("a/../../root/b"), None);
+        _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn absolute_paths_start_at_the_root()
+    {
+        let (mut fs, root, _) = sandbox("absolute");
+        fs.cwd = root.join("a");
+        assert_eq!(fs._confine("/etc/passwd"), Some(root.join("etc/passwd")));
+        assert_eq!(fs._confine("b"), Some(root.join("a/b")));
+        assert_eq!(fs._confine("/../etc"), None);
+        _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn symlinks_have_to_resolve_inside()
+    {
+        let (fs, root, outside) = sandbox("symlink");
+        symlink(&outside, root.join("out")).unwrap();
+        symlink(outside.join("missing"), root.join("dangling")).unwrap();
+        symlink(root.join("a"), root.join("in")).unwrap();
+        assert_eq!(fs._confine("out"), None);
+        assert_eq!(fs._confine("out/new"), None);
+        assert_eq!(fs._confine("dangling"), None);
+        assert_eq!(fs._confine("in/new"), Some(root.join("in/new")));
+        _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
 
 }
@@ -0,0 +1,77 @@
+#![allow(non_snake_case)]
+use erebos::instructions::*;
+use std::cell::RefCell;
+use crate::vm::InterruptID;
+
+/// one guest load or store, a 16 or 32 bit access is a single one
+/// -> virtual is the address the guest used, physical is where it landed after the memory maps
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryAccess
+{
+    pub virtual_adr: u32,
+    pub physical_adr: u32,
+    /// bytes
+    pub size: u8,
+    pub value: u32,
+}
+
+pub type InstructionHook = Box<dyn FnMut(usize, u32, &IRInstruction)>;
+pub type MemoryHook      = Box<dyn FnMut(usize, MemoryAccess)>;
+pub type InterruptHook   = Box<dyn FnMut(usize, InterruptID)>;
+pub type IOHook          = Box<dyn FnMut(usize, u16, u32)>;
+pub type HaltHook        = Box<dyn FnMut(usize, u32)>;
+
+/// callbacks for tools built around the vm [tracing, coverage, profiling, ...]
+/// -> they only get values, not the vm, so they cant change what the guest sees
+/// -> every list is behind a RefCell, so reads that only borrow the vm can call them too
+#[derive(Default)]
+pub struct HOOKS
+{
+    instruction: RefCell<Vec<InstructionHook>>,
+    memory_read: RefCell<Vec<MemoryHook>>,
+    memory_write: RefCell<Vec<MemoryHook>>,
+    interrupt: RefCell<Vec<InterruptHook>>,
+    io: RefCell<Vec<IOHook>>,
+    halt: RefCell<Vec<HaltHook>>,
+}
+impl HOOKS
+{
+
+    pub fn new() -> Self { Self::default() }
+
+    pub fn AddInstruction(&mut self, hook: InstructionHook) { self.instruction.get_mut().push(hook); }
+    pub fn AddMemoryRead (&mut self, hook: MemoryHook)      { self.memory_read.get_mut().push(hook); }
+    pub fn AddMemoryWrite(&mut self, hook: MemoryHook)      { self.memory_write.get_mut().push(hook); }
+    pub fn AddInterrupt  (&mut self, hook: InterruptHook)   { self.interrupt.get_mut().push(hook); }
+    pub fn AddIO         (&mut self, hook: IOHook)          { self.io.get_mut().push(hook); }
+    pub fn AddHalt       (&mut self, hook: HaltHook)        { self.halt.get_mut().push(hook); }
+
+    /// (core, rip of the instruction, instruction) right before it is executed
+    pub fn Instruction(&self, core: usize, ip: u32, ins: &IRInstruction)
+    {
+        for h in self.instruction.borrow_mut().iter_mut() { h(core, ip, ins); }
+    }
+    pub fn MemoryRead(&self, core: usize, access: MemoryAccess)
+    {
+        for h in self.memory_read.borrow_mut().iter_mut() { h(core, access); }
+    }
+    pub fn MemoryWrite(&self, core: usize, access: MemoryAccess)
+    {
+        for h in self.memory_write.borrow_mut().iter_mut() { h(core, access); }
+    }
+    pub fn Interrupt(&self, core: usize, id: InterruptID)
+    {
+        for h in self.interrupt.borrow_mut().iter_mut() { h(core, id); }
+    }
+    /// (core, selected io device, io instruction), F0 and above dont go to the device
+    pub fn IO(&self, core: usize, device: u16, ins: u32)
+    {
+        for h in self.io.borrow_mut().iter_mut() { h(core, device, ins); }
+    }
+    /// (core, rip after the hlt or Exit())
+    pub fn Halt(&self, core: usize, ip: u32)
+    {
+        for h in self.halt.borrow_mut().iter_mut() { h(core, ip); }
+    }
+
+}
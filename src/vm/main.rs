@@ -9,7 +9,20 @@ use erebos::debug::*;
 
 pub mod vm;
 pub mod fs;
+pub mod memfs;
 pub mod ray;
+pub mod canvas;
+pub mod png;
+pub mod rng;
+pub mod disk;
+pub mod net;
+pub mod channel;
+#[cfg(feature = "terminal")]
+pub mod console;
+pub mod display;
+pub mod audio;
+pub mod perf;
+pub mod hooks;
 
 fn print_err(e: impl std::fmt::Display)
 {
@@ -29,12 +42,45 @@ fn handle_err<T>(t: Result<T, Error>) -> Option<T>
     }
 }
 
+// process exit codes, a guest Exit() gives its own status [0 to 199]
+const EXIT_VM_ERROR: i32 = 200;
+const EXIT_BUDGET  : i32 = 201;
+const EXIT_STARTUP : i32 = 202;
+
 fn main()
+{
+    std::process::exit(vm_main());
+}
+
+fn vm_main() -> i32
 {
 
     let mut _enable_debug_print  = false;
     let mut _enable_section_mode = false;
     let mut _register_dump = false;
+    let mut _deterministic = false;
+    let mut seed: Option<u64> = None;
+    let mut disk_file: Option<String> = None;
+    let mut net_allow: Vec<String> = Vec::new();
+    let mut channel_listen: Option<String> = None;
+    let mut channel_connect: Option<String> = None;
+    let mut display_adr: Option<u32> = None;
+    let mut display_dump: Option<String> = None;
+    let mut audio_wav: Option<String> = None;
+    let mut ray_headless: Option<u32> = None;
+    let mut record_frames: Option<String> = None;
+    let mut record_every: Option<u32> = None;
+    let mut fs_root: Option<String> = None;
+    let mut fs_memory: Option<String> = None;
+    let mut fs_dump: Option<String> = None;
+    let mut _fs_read_only = false;
+    // everything after -- goes to the guest, argv[0] is the input file
+    let mut guest_args: Option<Vec<String>> = None;
+    let mut guest_env: Vec<String> = Vec::new();
+
+    let mut core_count: usize = 1;
+    let mut core_slice: u32 = 100;
+    let mut budget: u64 = 0;
 
     let mut infile: Option<String> = None;
     let mut symbol_file: Option<String> = None;
@@ -47,6 +93,245 @@ fn main()
             "-d" => _enable_debug_print  = true,
             "-s" => _enable_section_mode = true,
             "-r" => _register_dump = true,
+            "--deterministic" => _deterministic = true,
+
+            "--" =>
+            {
+                guest_args = Some(args.by_ref().collect());
+            },
+            "--env" =>
+            {
+                match args.next()
+                {
+                    Some(s) if s.contains('=') => guest_env.push(s),
+                    _ =>
+                    {
+                        print_err("Expected KEY=VALUE after --env!");
+                        return EXIT_STARTUP;
+                    }
+                }
+            },
+
+            "-c" =>
+            {
+                core_count = match args.next().and_then(|a| a.parse::<usize>().ok())
+                {
+                    Some(n) if n > 0 => n,
+                    _ =>
+                    {
+                        print_err("Expected core count after -c!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--seed" =>
+            {
+                seed = match args.next().and_then(|a| a.parse::<u64>().ok())
+                {
+                    Some(n) => Some(n),
+                    None =>
+                    {
+                        print_err("Expected number after --seed!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--fs-read-only" => _fs_read_only = true,
+            "--fs-root" =>
+            {
+                fs_root = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected directory after --fs-root!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--fs-memory" =>
+            {
+                fs_memory = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected tar archive or directory after --fs-memory!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--fs-dump" =>
+            {
+                fs_dump = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected file after --fs-dump!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--net-allow" =>
+            {
+                match args.next()
+                {
+                    Some(s) => net_allow.push(s),
+                    None =>
+                    {
+                        print_err("Expected host after --net-allow!");
+                        return EXIT_STARTUP;
+                    }
+                }
+            },
+            "--channel-listen" =>
+            {
+                channel_listen = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected socket path after --channel-listen!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--channel-connect" =>
+            {
+                channel_connect = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected socket path after --channel-connect!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--display" =>
+            {
+                let adr = args.next().and_then(|a| match a.strip_prefix("0x")
+                {
+                    Some(h) => u32::from_str_radix(h, 16).ok(),
+                    None => a.parse::<u32>().ok(),
+                });
+                display_adr = match adr
+                {
+                    Some(a) => Some(a),
+                    None =>
+                    {
+                        print_err("Expected address after --display!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--display-dump" =>
+            {
+                display_dump = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected file after --display-dump!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--disk" =>
+            {
+
+                let a = match args.next()
+                {
+                    Some(s) => s,
+                    None =>
+                    {
+                        print_err("Expected disk image after --disk!");
+                        return EXIT_STARTUP;
+                    }
+                };
+
+                if(!Path::new(&a).is_file())
+                {
+                    print_err(format!("Disk image '{a}' is not a file!"));
+                    return EXIT_STARTUP;
+                }
+
+                disk_file = Some(a);
+
+            },
+            "--audio-wav" =>
+            {
+                audio_wav = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected file after --audio-wav!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--ray-headless" =>
+            {
+                ray_headless = match args.next().and_then(|a| a.parse::<u32>().ok())
+                {
+                    Some(n) => Some(n),
+                    None =>
+                    {
+                        print_err("Expected frame count after --ray-headless!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--record-frames" =>
+            {
+                record_frames = match args.next()
+                {
+                    Some(s) => Some(s),
+                    None =>
+                    {
+                        print_err("Expected directory after --record-frames!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--record-every" =>
+            {
+                record_every = match args.next().and_then(|a| a.parse::<u32>().ok())
+                {
+                    Some(n) => Some(n),
+                    None =>
+                    {
+                        print_err("Expected frame interval after --record-every!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "--budget" =>
+            {
+                budget = match args.next().and_then(|a| a.parse::<u64>().ok())
+                {
+                    Some(n) if n > 0 => n,
+                    _ =>
+                    {
+                        print_err("Expected instruction count after --budget!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
+            "-t" =>
+            {
+                core_slice = match args.next().and_then(|a| a.parse::<u32>().ok())
+                {
+                    Some(n) if n > 0 => n,
+                    _ =>
+                    {
+                        print_err("Expected amount of instructions per time slice after -t!");
+                        return EXIT_STARTUP;
+                    }
+                };
+            },
 
             "-f" =>
             {
@@ -57,7 +342,7 @@ fn main()
                     None =>
                     {
                         print_err("Expected file after -s!");
-                        return;
+                        return EXIT_STARTUP;
                     }
                 };
 
@@ -66,13 +351,13 @@ fn main()
                 if(!path.exists())
                 {
                     print_err(format!("'{a}' doesnt exist!"));
-                    return;
+                    return EXIT_STARTUP;
                 }
 
                 if(!path.is_file())
                 {
                     print_err(format!("'{a}' is not a file!"));
-                    return;
+                    return EXIT_STARTUP;
                 }
 
                 symbol_file = Some(a);
@@ -85,7 +370,7 @@ fn main()
                 if let Some(ref f) = infile
                 {
                     print_err(format!("Multiple input files specified! Only one file can be executed. ({}, {})", f, a));
-                    return;
+                    return EXIT_STARTUP;
                 }
 
                 let path = Path::new(&a);
@@ -93,13 +378,13 @@ fn main()
                 if(!path.exists())
                 {
                     print_err(format!("'{a}' doesnt exist!"));
-                    return;
+                    return EXIT_STARTUP;
                 }
 
                 if(!path.is_file())
                 {
                     print_err(format!("'{a}' is not a file!"));
-                    return;
+                    return EXIT_STARTUP;
                 }
 
                 infile = Some(a);
@@ -114,7 +399,7 @@ fn main()
         None =>
         {
             print_err("No input file specified!".to_string());
-            return;
+            return EXIT_STARTUP;
         }
     };
 
@@ -129,14 +414,14 @@ fn main()
                     if(d.symbols.is_empty())
                     {
                         print_err("Cannot use empty symbols file!");
-                        return;
+                        return EXIT_STARTUP;
                     }
                     d
                 },
                 Err(e) =>
                 {
                     print_err(e);
-                    return;
+                    return EXIT_STARTUP;
                 }
             }
         ),
@@ -146,23 +431,186 @@ fn main()
 
     let mut vm = VM::new();
 
-    let bytes = match read(infile)
+    let bytes = match read(&infile)
     {
         Ok(s) => s,
         Err(e) =>
         {
             print_err(format!("Error reading file: {e}"));
-            return;
+            return EXIT_STARTUP;
         }
     };
 
-    let _ = vm.load_executable(bytes);
+    if(fs_memory.is_some() && fs_root.is_some())
+    {
+        print_err("--fs-root and --fs-memory can not be used together!");
+        return EXIT_STARTUP;
+    }
+    if(fs_dump.is_some() && fs_memory.is_none())
+    {
+        print_err("--fs-dump needs --fs-memory!");
+        return EXIT_STARTUP;
+    }
+
+    // has to be in place before the header can set the files location
+    if let Some(fs_memory) = fs_memory
+    {
+        if handle_err(vm.set_fs_memory(&fs_memory)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+    if let Some(fs_root) = fs_root
+    {
+        if handle_err(vm.set_fs_root(&fs_root)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+    if(_fs_read_only) { vm.set_fs_read_only(); }
+
+    if handle_err(vm.load_executable(bytes)).is_none()
+    {
+        return EXIT_STARTUP;
+    }
+
+    // ra stays 0 without --
+    if(guest_args.is_some() || !guest_env.is_empty())
+    {
+        let mut argv = vec![infile.clone()];
+        argv.extend(guest_args.unwrap_or_default());
+        if handle_err(vm.set_args(&argv, &guest_env)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
 
     if(_enable_debug_print ) { vm.enable_debug_print (); }
     if(_enable_section_mode) { vm.enable_section_mode(); }
+    if(_deterministic      ) { vm.enable_deterministic(); }
+
+    // deterministic runs get a fixed seed too, unless one was given
+    if let Some(seed) = seed.or(if(_deterministic) { Some(0) } else { None })
+    {
+        vm.set_seed(seed);
+    }
+
+    if let Some(disk_file) = disk_file
+    {
+        if handle_err(vm.attach_disk(&disk_file)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+
+    for host in net_allow
+    {
+        if handle_err(vm.allow_net_host(&host)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
 
-    if handle_err(vm.run()).is_none()
+    // a dump means nobody is watching, so nothing is drawn
+    match (display_adr, &display_dump)
     {
+        (Some(adr), dump) =>
+        {
+            if handle_err(vm.enable_display(adr, dump.is_some())).is_none()
+            {
+                return EXIT_STARTUP;
+            }
+        },
+        (None, Some(_)) =>
+        {
+            print_err("--display-dump needs --display!");
+            return EXIT_STARTUP;
+        },
+        (None, None) => {},
+    }
+
+    if(audio_wav.is_some())
+    {
+        vm.record_audio();
+    }
+    if let Some(frames) = ray_headless
+    {
+        if handle_err(vm.set_ray_headless(frames)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+    match (record_frames, record_every)
+    {
+        (Some(dir), every) =>
+        {
+            if handle_err(vm.record_frames(&dir, every.unwrap_or(1))).is_none()
+            {
+                return EXIT_STARTUP;
+            }
+        },
+        (None, Some(_)) =>
+        {
+            print_err("--record-every needs --record-frames!");
+            return EXIT_STARTUP;
+        },
+        (None, None) => {},
+    }
+
+    if(channel_listen.is_some() && channel_connect.is_some())
+    {
+        print_err("--channel-listen and --channel-connect can not be used together!");
+        return EXIT_STARTUP;
+    }
+    if let Some(path) = channel_listen
+    {
+        println!("Waiting for the other vm on '{path}'...");
+        if handle_err(vm.listen_channel(&path)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+    if let Some(path) = channel_connect
+    {
+        if handle_err(vm.connect_channel(&path)).is_none()
+        {
+            return EXIT_STARTUP;
+        }
+    }
+
+    vm.set_core_count(core_count);
+    vm.set_core_slice(core_slice);
+    vm.set_instruction_budget(budget);
+
+    let result = vm.run();
+
+    // the messages below should land on the normal screen
+    vm.restore_console();
+
+    // written even if the guest crashed, it may be what is being looked for
+    if let Some(fs_dump) = fs_dump
+    {
+        handle_err(vm.dump_fs(&fs_dump));
+    }
+    if let (Some(path), Some(text)) = (display_dump, vm.display_text())
+    {
+        if let Err(e) = std::fs::write(&path, text)
+        {
+            print_err(format!("Couldnt write display dump '{path}': {e}"));
+        }
+    }
+    if let Some(path) = audio_wav
+    {
+        handle_err(vm.write_audio(&path));
+    }
+
+    if handle_err(result).is_none()
+    {
+
+        if(vm.core_count() > 1)
+        {
+            println!("On core {}", vm.core_id());
+        }
 
         let rip = vm.instruction_pointer;
         let rip_in_file = rip.overflowing_add(32).0;
@@ -186,18 +634,25 @@ fn main()
             {
                 pre_line.push_str("     ");
             }
-            print!("{}", format!("{:#04x} ", vm.memget(ptr).unwrap()).blue());
+            print!("{}", format!("{:#04x} ", vm.peek(ptr)).blue());
         }
         println!();
 
         println!("{pre_line}{}", "^^^^".bright_blue());
 
-        return;
+        return EXIT_VM_ERROR;
 
     }
 
     println!("{}", "\n\r-------------------------".magenta());
-    println!("\rExecution finished!");
+    if(vm.budget_exhausted())
+    {
+        println!("\rInstruction budget of {} exhausted!", budget);
+    }
+    else
+    {
+        println!("\rExecution finished!");
+    }
 
     if(_register_dump)
     {
@@ -211,4 +666,13 @@ fn main()
     
     println!("{}", "\r-------------------------".magenta());
 
+    if(vm.budget_exhausted())
+    {
+        EXIT_BUDGET
+    }
+    else
+    {
+        vm.exit_status().map(|s| s.min(199) as i32).unwrap_or(0)
+    }
+
 }
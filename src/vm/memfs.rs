@@ -0,0 +1,394 @@
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{ErrorKind, Result as IOResult};
+use std::path::{Component, Path, PathBuf};
+
+const TAR_BLOCK: usize = 512;
+// the guest picks offsets and lengths, without a cap one seek far past the end would take gigabytes of host memory
+const MEMFS_MAX_FILE : u64 =  16 * 1024 * 1024;
+const MEMFS_MAX_TOTAL: u64 = 256 * 1024 * 1024;
+
+fn io_err<T>(kind: ErrorKind) -> IOResult<T> { Err(kind.into()) }
+
+/// file tree that only lives in memory, paths are absolute starting at /
+/// -> loaded from a tar archive or a host directory, nothing ever touches the host after that
+pub struct MEMFS
+{
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+}
+impl Default for MEMFS
+{
+    fn default() -> Self { Self::new() }
+}
+impl MEMFS
+{
+
+    pub fn new() -> Self
+    {
+        let mut dirs = BTreeSet::new();
+        dirs.insert(PathBuf::from("/"));
+        Self
+        {
+            files: BTreeMap::new(),
+            dirs,
+        }
+    }
+
+    /// a directory gets copied, anything else is read as a tar archive
+    pub fn from_source(src: &str) -> Result<Self, Error>
+    {
+        let mut fs = Self::new();
+        if(Path::new(src).is_dir())
+        {
+            fs.load_dir(Path::new(src), Path::new("/"))?;
+        }
+        else
+        {
+            let bytes = match std::fs::read(src)
+            {
+                Ok(b) => b,
+                Err(e) => return Err(error!("Couldnt read archive '{}': {}", src, e)),
+            };
+            fs.load_tar(&bytes)?;
+        }
+        Ok(fs)
+    }
+
+    /// guest path inside the archive, .. and the like are dropped
+    fn normalize(name: &str) -> PathBuf
+    {
+        let mut p = PathBuf::from("/");
+        for c in Path::new(name).components()
+        {
+            if let Component::Normal(n) = c
+            {
+                p.push(n);
+            }
+        }
+        p
+    }
+    fn add_parents(&mut self, path: &Path)
+    {
+        let mut p = path.parent();
+        while let Some(d) = p
+        {
+            self.dirs.insert(d.to_path_buf());
+            p = d.parent();
+        }
+    }
+
+    fn load_dir(&mut self, host: &Path, guest: &Path) -> Result<(), Error>
+    {
+
+        let dir = match std::fs::read_dir(host)
+        {
+            Ok(d) => d,
+            Err(e) => return Err(Error::fromio(e)),
+        };
+
+        for entry in dir.flatten()
+        {
+            // symlinks are skipped, they could point anywhere [or loop]
+            let Ok(t) = entry.file_type()
+            else { continue; };
+            let g = guest.join(entry.file_name());
+            if(t.is_dir())
+            {
+                self.dirs.insert(g.clone());
+                self.load_dir(&entry.path(), &g)?;
+            }
+            else if(t.is_file())
+            {
+                match std::fs::read(entry.path())
+                {
+                    Ok(b) => { self.files.insert(g, b); },
+                    Err(e) => return Err(Error::fromio(e)),
+                }
+            }
+        }
+
+        Ok(())
+
+    }
+
+    fn tar_str(bytes: &[u8]) -> String
+    {
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).to_string()
+    }
+    fn tar_num(bytes: &[u8]) -> usize
+    {
+        let s = MEMFS::tar_str(bytes);
+        usize::from_str_radix(s.trim(), 8).unwrap_or(0)
+    }
+
+    /// ustar, only files and directories are taken, everything else is skipped
+    fn load_tar(&mut self, bytes: &[u8]) -> Result<(), Error>
+    {
+
+        let mut pos = 0;
+
+        while(pos + TAR_BLOCK <= bytes.len())
+        {
+
+            let header = &bytes[pos..pos + TAR_BLOCK];
+            pos += TAR_BLOCK;
+
+            if(header.iter().all(|b| *b == 0)) { break; }
+
+            let mut name = MEMFS::tar_str(&header[0..100]);
+            if(&header[257..262] == b"ustar")
+            {
+                let prefix = MEMFS::tar_str(&header[345..500]);
+                if(!prefix.is_empty())
+                {
+                    name = format!("{prefix}/{name}");
+                }
+            }
+            let size = MEMFS::tar_num(&header[124..136]);
+
+            if(pos + size > bytes.len())
+            {
+                return Err(error!("Tar archive ends in the middle of '{}'!", name));
+            }
+
+            let path = MEMFS::normalize(&name);
+            match header[156]
+            {
+                b'0' | 0 =>
+                {
+                    self.add_parents(&path);
+                    self.files.insert(path, bytes[pos..pos + size].to_vec());
+                },
+                b'5' =>
+                {
+                    self.add_parents(&path);
+                    self.dirs.insert(path);
+                },
+                _ => {},
+            }
+
+            pos += size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+
+        }
+
+        Ok(())
+
+    }
+
+    fn tar_header(name: &str, size: usize, dir: bool) -> Result<[u8; TAR_BLOCK], Error>
+    {
+
+        let mut h = [0u8; TAR_BLOCK];
+
+        // long names get split into prefix/name at a /
+        let (prefix, name) = if(name.len() <= 100)
+        {
+            ("", name)
+        }
+        else
+        {
+            match name.char_indices().rfind(|(i, c)| *c == '/' && *i <= 155 && name.len() - i - 1 <= 100)
+            {
+                Some((i, _)) => (&name[..i], &name[i + 1..]),
+                None => return Err(error!("'{}' is too long to be stored in a tar archive!", name)),
+            }
+        };
+
+        let mut put = |off: usize, s: &[u8]| h[off..off + s.len()].copy_from_slice(s);
+        put(0, name.as_bytes());
+        put(100, if(dir) { b"0000755\0" } else { b"0000644\0" });
+        put(108, b"0000000\0");
+        put(116, b"0000000\0");
+        put(124, format!("{:011o}\0", size).as_bytes());
+        put(136, b"00000000000\0");
+        put(148, b"        ");
+        put(156, if(dir) { b"5" } else { b"0" });
+        put(257, b"ustar\0");
+        put(263, b"00");
+        put(345, prefix.as_bytes());
+
+        let sum: u32 = h.iter().map(|b| *b as u32).sum();
+        h[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+
+        Ok(h)
+
+    }
+    pub fn dump_tar(&self, path: &str) -> Result<(), Error>
+    {
+
+        let mut out: Vec<u8> = Vec::new();
+
+        for d in &self.dirs
+        {
+            let name = d.to_string_lossy();
+            let name = name.trim_start_matches('/');
+            if(name.is_empty()) { continue; }
+            out.extend(MEMFS::tar_header(&format!("{name}/"), 0, true)?);
+        }
+        for (f, data) in &self.files
+        {
+            let name = f.to_string_lossy();
+            out.extend(MEMFS::tar_header(name.trim_start_matches('/'), data.len(), false)?);
+            out.extend(data);
+            out.resize(out.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+        }
+        out.resize(out.len() + 2 * TAR_BLOCK, 0);
+
+        match std::fs::write(path, out)
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(error!("Couldnt write archive '{}': {}", path, e)),
+        }
+
+    }
+
+
+
+    pub fn is_dir (&self, p: &Path) -> bool { self.dirs.contains(p) }
+    pub fn is_file(&self, p: &Path) -> bool { self.files.contains_key(p) }
+    pub fn exists (&self, p: &Path) -> bool { self.is_dir(p) || self.is_file(p) }
+
+    fn used(&self) -> u64
+    {
+        self.files.values().map(|b| b.len() as u64).sum()
+    }
+    /// growing a file to len has to stay inside the limits, shrinking always works
+    fn check_size(&self, p: &Path, len: u64) -> IOResult<()>
+    {
+        let old = self.files.get(p).map(|b| b.len() as u64).unwrap_or(0);
+        if(len <= old) { return Ok(()); }
+        if(len > MEMFS_MAX_FILE) { return io_err(ErrorKind::FileTooLarge); }
+        if(self.used() - old + len > MEMFS_MAX_TOTAL) { return io_err(ErrorKind::StorageFull); }
+        Ok(())
+    }
+
+    fn check_parent(&self, p: &Path) -> IOResult<()>
+    {
+        match p.parent()
+        {
+            Some(d) if self.is_dir(d) => Ok(()),
+            _ => io_err(ErrorKind::NotFound),
+        }
+    }
+
+    pub fn read(&self, p: &Path) -> IOResult<Vec<u8>>
+    {
+        match self.files.get(p)
+        {
+            Some(b) => Ok(b.clone()),
+            None => io_err(ErrorKind::NotFound),
+        }
+    }
+    pub fn read_at(&self, p: &Path, pos: u64, n: u64) -> IOResult<Vec<u8>>
+    {
+        let Some(b) = self.files.get(p)
+        else { return io_err(ErrorKind::NotFound); };
+        let start = (pos as usize).min(b.len());
+        let end   = (pos.saturating_add(n) as usize).min(b.len());
+        Ok(b[start..end].to_vec())
+    }
+    /// creates the file if its missing
+    pub fn write(&mut self, p: &Path, data: &[u8]) -> IOResult<()>
+    {
+        if(self.is_dir(p)) { return io_err(ErrorKind::IsADirectory); }
+        self.check_parent(p)?;
+        self.check_size(p, data.len() as u64)?;
+        self.files.insert(p.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+    /// writes past the end fill the gap with 0
+    pub fn write_at(&mut self, p: &Path, pos: u64, data: &[u8]) -> IOResult<()>
+    {
+        if(!self.is_file(p)) { return io_err(ErrorKind::NotFound); }
+        self.check_size(p, pos.saturating_add(data.len() as u64))?;
+        let Some(b) = self.files.get_mut(p)
+        else { return io_err(ErrorKind::NotFound); };
+        let pos = pos as usize;
+        if(b.len() < pos + data.len())
+        {
+            b.resize(pos + data.len(), 0);
+        }
+        b[pos..pos + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+    pub fn set_len(&mut self, p: &Path, len: u64) -> IOResult<()>
+    {
+        if(self.is_file(p)) { self.check_size(p, len)?; }
+        match self.files.get_mut(p)
+        {
+            Some(b) => { b.resize(len as usize, 0); Ok(()) },
+            None => io_err(ErrorKind::NotFound),
+        }
+    }
+    pub fn len(&self, p: &Path) -> IOResult<u64>
+    {
+        match self.files.get(p)
+        {
+            Some(b) => Ok(b.len() as u64),
+            None if self.is_dir(p) => Ok(0),
+            None => io_err(ErrorKind::NotFound),
+        }
+    }
+    pub fn is_empty(&self) -> bool { self.files.is_empty() && self.dirs.len() == 1 }
+
+    pub fn create_dir(&mut self, p: &Path) -> IOResult<()>
+    {
+        if(self.exists(p)) { return io_err(ErrorKind::AlreadyExists); }
+        self.check_parent(p)?;
+        self.dirs.insert(p.to_path_buf());
+        Ok(())
+    }
+    pub fn remove_dir(&mut self, p: &Path) -> IOResult<()>
+    {
+        if(!self.is_dir(p)) { return io_err(ErrorKind::NotFound); }
+        if(p.parent().is_none()) { return io_err(ErrorKind::PermissionDenied); } // the root stays
+        if(!self.list(p)?.is_empty()) { return io_err(ErrorKind::DirectoryNotEmpty); }
+        self.dirs.remove(p);
+        Ok(())
+    }
+    pub fn remove_file(&mut self, p: &Path) -> IOResult<()>
+    {
+        match self.files.remove(p)
+        {
+            Some(_) => Ok(()),
+            None => io_err(ErrorKind::NotFound),
+        }
+    }
+    /// moves everything below a directory along with it
+    pub fn rename(&mut self, from: &Path, to: &Path) -> IOResult<()>
+    {
+        if(!self.exists(from)) { return io_err(ErrorKind::NotFound); }
+        if(from.parent().is_none()) { return io_err(ErrorKind::PermissionDenied); }
+        if(to.starts_with(from)) { return io_err(ErrorKind::InvalidInput); } // into itself
+        if(self.exists(to)) { return io_err(ErrorKind::AlreadyExists); }
+        self.check_parent(to)?;
+
+        let files: Vec<PathBuf> = self.files.keys().filter(|k| k.starts_with(from)).cloned().collect();
+        for f in files
+        {
+            let data = self.files.remove(&f).unwrap_or_default();
+            self.files.insert(to.join(f.strip_prefix(from).unwrap_or(&f)), data);
+        }
+        let dirs: Vec<PathBuf> = self.dirs.iter().filter(|k| k.starts_with(from)).cloned().collect();
+        for d in dirs
+        {
+            self.dirs.remove(&d);
+            self.dirs.insert(to.join(d.strip_prefix(from).unwrap_or(&d)));
+        }
+        Ok(())
+    }
+    /// full paths of everything directly in the directory
+    pub fn list(&self, p: &Path) -> IOResult<Vec<PathBuf>>
+    {
+        if(!self.is_dir(p)) { return io_err(ErrorKind::NotFound); }
+        Ok(self.dirs.iter().chain(self.files.keys())
+            .filter(|k| k.parent() == Some(p))
+            .cloned()
+            .collect())
+    }
+
+}
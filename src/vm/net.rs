@@ -0,0 +1,263 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const NET_MAX_SOCKETS: usize = 64;
+const NET_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// most one Send() or Receive() moves, the guest loops for more
+pub const NET_MAX_TRANSFER: u32 = 64 * 1024;
+
+#[repr(u8)]
+#[derive(PartialEq)]
+pub enum NetResult
+{
+    OK = 0,
+    InvalidAddress,
+    NotAllowed,
+    InvalidHandle,
+    TooManySockets,
+    CouldntConnect,
+    CouldntListen,
+    WouldBlock,
+    Closed,
+    IsntStream,
+    IsntListener,
+}
+
+pub enum Socket
+{
+    Listener(TcpListener),
+    Stream(TcpStream),
+}
+
+/// tcp sockets by handle, all non blocking, only to and on addresses in the allow list
+pub struct NET
+{
+    allowed: Vec<IpAddr>,
+    sockets: HashMap<u32, Socket>,
+    next_handle: u32,
+}
+impl Default for NET
+{
+    fn default() -> Self { Self::new() }
+}
+impl NET
+{
+
+    /// localhost only
+    pub fn new() -> Self
+    {
+        Self
+        {
+            allowed: vec![ IpAddr::from([127, 0, 0, 1]), IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]) ],
+            sockets: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    /// host names are resolved once, here
+    pub fn Allow(&mut self, host: &str) -> Result<(), Error>
+    {
+        let addrs = match (host, 0).to_socket_addrs()
+        {
+            Ok(a) => a,
+            Err(e) => return Err(error!("Couldnt resolve '{}' for --net-allow: {}", host, e)),
+        };
+        for a in addrs
+        {
+            if(!self.allowed.contains(&a.ip())) { self.allowed.push(a.ip()); }
+        }
+        Ok(())
+    }
+
+    /// "host:port", every address it resolves to has to be allowed
+    fn _resolve(&self, addr: &str) -> Result<Vec<SocketAddr>, NetResult>
+    {
+        let addrs: Vec<SocketAddr> = match addr.to_socket_addrs()
+        {
+            Ok(a) => a.collect(),
+            Err(_) => return Err(NetResult::InvalidAddress),
+        };
+        if(addrs.is_empty())
+        {
+            return Err(NetResult::InvalidAddress);
+        }
+        if(!addrs.iter().all(|a| self.allowed.contains(&a.ip())))
+        {
+            return Err(NetResult::NotAllowed);
+        }
+        Ok(addrs)
+    }
+
+    fn _add(&mut self, socket: Socket) -> (NetResult, Option<u32>)
+    {
+        if(self.sockets.len() >= NET_MAX_SOCKETS)
+        {
+            return (NetResult::TooManySockets, None);
+        }
+        while(self.next_handle == 0 || self.sockets.contains_key(&self.next_handle))
+        {
+            self.next_handle = self.next_handle.wrapping_add(1);
+        }
+        let handle = self.next_handle;
+        self.next_handle = self.next_handle.wrapping_add(1);
+        self.sockets.insert(handle, socket);
+        (NetResult::OK, Some(handle))
+    }
+
+    pub fn Connect(&mut self, addr: &str) -> (NetResult, Option<u32>)
+    {
+
+        let addrs = match self._resolve(addr)
+        {
+            Ok(a) => a,
+            Err(r) => return (r, None),
+        };
+        if(self.sockets.len() >= NET_MAX_SOCKETS)
+        {
+            return (NetResult::TooManySockets, None);
+        }
+
+        // the connect itself blocks, everything after it doesnt
+        let Some(stream) = addrs.iter().find_map(|a| TcpStream::connect_timeout(a, NET_CONNECT_TIMEOUT).ok())
+        else { return (NetResult::CouldntConnect, None); };
+        if(stream.set_nonblocking(true).is_err())
+        {
+            return (NetResult::CouldntConnect, None);
+        }
+
+        self._add(Socket::Stream(stream))
+
+    }
+
+    pub fn Listen(&mut self, addr: &str) -> (NetResult, Option<u32>)
+    {
+
+        let addrs = match self._resolve(addr)
+        {
+            Ok(a) => a,
+            Err(r) => return (r, None),
+        };
+        if(self.sockets.len() >= NET_MAX_SOCKETS)
+        {
+            return (NetResult::TooManySockets, None);
+        }
+
+        let listener = match TcpListener::bind(&addrs[..])
+        {
+            Ok(l) => l,
+            Err(_) => return (NetResult::CouldntListen, None),
+        };
+        if(listener.set_nonblocking(true).is_err())
+        {
+            return (NetResult::CouldntListen, None);
+        }
+
+        self._add(Socket::Listener(listener))
+
+    }
+
+    pub fn Accept(&mut self, handle: u32) -> (NetResult, Option<u32>)
+    {
+
+        let listener = match self.sockets.get(&handle)
+        {
+            Some(Socket::Listener(l)) => l,
+            Some(_) => return (NetResult::IsntListener, None),
+            None => return (NetResult::InvalidHandle, None),
+        };
+
+        let stream = match listener.accept()
+        {
+            Ok((s, _)) => s,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return (NetResult::WouldBlock, None),
+            Err(_) => return (NetResult::Closed, None),
+        };
+        if(stream.set_nonblocking(true).is_err())
+        {
+            return (NetResult::Closed, None);
+        }
+
+        self._add(Socket::Stream(stream))
+
+    }
+
+    fn _stream(&mut self, handle: u32) -> Result<&mut TcpStream, NetResult>
+    {
+        match self.sockets.get_mut(&handle)
+        {
+            Some(Socket::Stream(s)) => Ok(s),
+            Some(_) => Err(NetResult::IsntStream),
+            None => Err(NetResult::InvalidHandle),
+        }
+    }
+
+    /// may send less than given
+    pub fn Send(&mut self, handle: u32, buffer: Vec<u8>) -> (NetResult, u32)
+    {
+        let stream = match self._stream(handle)
+        {
+            Ok(s) => s,
+            Err(r) => return (r, 0),
+        };
+        match stream.write(&buffer)
+        {
+            Ok(n) => (NetResult::OK, n as u32),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => (NetResult::WouldBlock, 0),
+            Err(_) => (NetResult::Closed, 0),
+        }
+    }
+
+    /// Closed once the other side is done and everything has been received
+    pub fn Receive(&mut self, handle: u32, n: u32) -> (NetResult, Option<Vec<u8>>)
+    {
+        let stream = match self._stream(handle)
+        {
+            Ok(s) => s,
+            Err(r) => return (r, None),
+        };
+        let mut buffer = vec![0; n.min(NET_MAX_TRANSFER) as usize];
+        match stream.read(&mut buffer)
+        {
+            Ok(0) if n > 0 => (NetResult::Closed, None),
+            Ok(r) =>
+            {
+                buffer.truncate(r);
+                (NetResult::OK, Some(buffer))
+            },
+            Err(e) if e.kind() == ErrorKind::WouldBlock => (NetResult::WouldBlock, None),
+            Err(_) => (NetResult::Closed, None),
+        }
+    }
+
+    /// port the socket is bound to locally, for listeners on port 0
+    pub fn GetPort(&self, handle: u32) -> (NetResult, u32)
+    {
+        let addr = match self.sockets.get(&handle)
+        {
+            Some(Socket::Listener(l)) => l.local_addr(),
+            Some(Socket::Stream(s)) => s.local_addr(),
+            None => return (NetResult::InvalidHandle, 0),
+        };
+        match addr
+        {
+            Ok(a) => (NetResult::OK, a.port() as u32),
+            Err(_) => (NetResult::Closed, 0),
+        }
+    }
+
+    pub fn Close(&mut self, handle: u32) -> NetResult
+    {
+        match self.sockets.remove(&handle)
+        {
+            Some(_) => NetResult::OK,
+            None => NetResult::InvalidHandle,
+        }
+    }
+
+}
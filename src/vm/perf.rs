@@ -0,0 +1,59 @@
+#![allow(non_snake_case)]
+use std::cell::Cell;
+
+pub const PERF_COUNTERS: usize = 5;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum PerfCounter
+{
+    Instructions = 0,
+    /// bytes, instruction fetches dont count
+    MemoryReads,
+    MemoryWrites,
+    Interrupts,
+    IOCalls,
+}
+
+/// counters the guest can read and reset, shared by all cores
+/// -> cells, so reads that only borrow the vm [jumps through memory] get counted as well
+pub struct PERF
+{
+    counters: [Cell<u64>; PERF_COUNTERS],
+}
+impl Default for PERF
+{
+    fn default() -> Self { Self::new() }
+}
+impl PERF
+{
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            counters: Default::default(),
+        }
+    }
+
+    pub fn Count(&self, counter: PerfCounter, n: u64)
+    {
+        let c = &self.counters[counter as usize];
+        c.set(c.get().wrapping_add(n));
+    }
+
+    /// None if there is no such counter
+    pub fn Read(&self, id: u32) -> Option<u64>
+    {
+        self.counters.get(id as usize).map(|c| c.get())
+    }
+
+    pub fn Reset(&self)
+    {
+        for c in &self.counters
+        {
+            c.set(0);
+        }
+    }
+
+}
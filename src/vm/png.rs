@@ -0,0 +1,188 @@
+#![allow(unused_parens)]
+use erebos::instructions::*;
+use erebos::error;
+use std::path::Path;
+
+// deflate length codes 257.. and distance codes 0.. [base, extra bits]
+const LENGTH_BASE : [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8;  29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE   : [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA  : [u8;  30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+const MAX_MATCH: usize = 258;
+const MAX_DIST : usize = 32768;
+
+/// deflate bit stream, least significant bit first
+struct Bits
+{
+    out: Vec<u8>,
+    acc: u32,
+    n: u32,
+}
+impl Bits
+{
+    fn put(&mut self, value: u32, count: u32)
+    {
+        self.acc |= value << self.n;
+        self.n += count;
+        while(self.n >= 8)
+        {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n -= 8;
+        }
+    }
+    /// huffman codes go most significant bit first
+    fn code(&mut self, code: u32, count: u32)
+    {
+        let mut rev = 0;
+        for i in 0..count
+        {
+            rev |= ((code >> i) & 1) << (count - 1 - i);
+        }
+        self.put(rev, count);
+    }
+    fn finish(mut self) -> Vec<u8>
+    {
+        if(self.n > 0) { self.out.push(self.acc as u8); }
+        self.out
+    }
+}
+
+/// fixed huffman table of deflate
+fn literal(bits: &mut Bits, v: u32)
+{
+    match v
+    {
+        0..=143   => bits.code(0x30 + v, 8),
+        144..=255 => bits.code(0x190 + v - 144, 9),
+        256..=279 => bits.code(v - 256, 7),
+        _         => bits.code(0xC0 + v - 280, 8),
+    }
+}
+fn matched(bits: &mut Bits, len: usize, dist: usize)
+{
+    let l = LENGTH_BASE.iter().rposition(|b| *b as usize <= len).unwrap_or(0);
+    literal(bits, 257 + l as u32);
+    bits.put((len - LENGTH_BASE[l] as usize) as u32, LENGTH_EXTRA[l] as u32);
+    let d = DIST_BASE.iter().rposition(|b| *b as usize <= dist).unwrap_or(0);
+    bits.code(d as u32, 5);
+    bits.put((dist - DIST_BASE[d] as usize) as u32, DIST_EXTRA[d] as u32);
+}
+
+/// one fixed huffman block, matches are only looked for one pixel back and one row up
+/// -> thats where the repetition in drawn frames is, so flat colors shrink a lot without a real search
+fn deflate(data: &[u8], stride: usize) -> Vec<u8>
+{
+
+    let mut bits = Bits { out: Vec::new(), acc: 0, n: 0 };
+    bits.put(1, 1); // last block
+    bits.put(1, 2); // fixed huffman
+
+    let mut i = 0;
+    while(i < data.len())
+    {
+        let mut best = (0, 0);
+        for dist in [3, stride]
+        {
+            if(dist == 0 || dist > i || dist > MAX_DIST) { continue; }
+            let mut len = 0;
+            while(len < MAX_MATCH && i + len < data.len() && data[i + len] == data[i + len - dist])
+            {
+                len += 1;
+            }
+            if(len > best.0) { best = (len, dist); }
+        }
+        if(best.0 >= 3)
+        {
+            matched(&mut bits, best.0, best.1);
+            i += best.0;
+        }
+        else
+        {
+            literal(&mut bits, data[i] as u32);
+            i += 1;
+        }
+    }
+    literal(&mut bits, 256);
+
+    bits.finish()
+
+}
+
+fn crc32(data: &[u8]) -> u32
+{
+    let mut crc = 0xFFFFFFFFu32;
+    for b in data
+    {
+        crc ^= *b as u32;
+        for _ in 0..8
+        {
+            crc = if(crc & 1 != 0) { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+fn adler32(data: &[u8]) -> u32
+{
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(4096)
+    {
+        for x in chunk
+        {
+            a += *x as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8])
+{
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// 8 bit rgb, rows top to bottom
+pub fn encode(w: u32, h: u32, rgb: &[u8]) -> Vec<u8>
+{
+
+    let stride = 1 + w as usize * 3;
+    let mut raw = Vec::with_capacity(stride * h as usize);
+    for row in rgb.chunks(w as usize * 3).take(h as usize)
+    {
+        raw.push(0); // no filter
+        raw.extend(row);
+    }
+
+    let mut z = vec![0x78, 0x01];
+    z.extend(deflate(&raw, stride));
+    z.extend(adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::new();
+    ihdr.extend(w.to_be_bytes());
+    ihdr.extend(h.to_be_bytes());
+    ihdr.extend([8, 2, 0, 0, 0]); // 8 bit, rgb, deflate, no filter method, no interlace
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &z);
+    chunk(&mut out, b"IEND", &[]);
+    out
+
+}
+
+pub fn write(path: &Path, w: u32, h: u32, rgb: &[u8]) -> Result<(), Error>
+{
+    match std::fs::write(path, encode(w, h, rgb))
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(error!("Couldnt write image '{}': {}", path.display(), e)),
+    }
+}
@@ -1,21 +1,52 @@
 #![allow(unused_parens)]
+#[cfg(feature = "raylib")]
 use raylib::prelude::*;
 
 use erebos::instructions::Error;
 use erebos::error;
+use std::path::PathBuf;
+use crate::canvas::CANVAS;
+use crate::png;
 
+#[cfg(feature = "raylib")]
 pub struct RAYHandler
 {
 
 }
+#[cfg(feature = "raylib")]
 impl RaylibDraw for RAYHandler {}
 
+/// stands in for the raylib color, without raylib only the headless canvas can be drawn into
+#[cfg(not(feature = "raylib"))]
+#[derive(Clone, Copy)]
+pub struct Color
+{
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
 pub struct RAY
 {
     is_drawing: bool,
+    #[cfg(feature = "raylib")]
     handler: RAYHandler,
+    #[cfg(feature = "raylib")]
     rl: Option<RaylibHandle>,
+    #[cfg(feature = "raylib")]
     thread: Option<RaylibThread>,
+    /// Some(frames) draws into a CANVAS instead of a window, WindowShouldClose() gives 1 after that many frames [0 never]
+    headless: Option<u32>,
+    canvas: Option<CANVAS>,
+    target_fps: u32,
+    /// frames ended since the window was opened
+    frame: u32,
+    /// every Nth frame is saved into record_dir, 0 only saves screenshots
+    record_dir: Option<PathBuf>,
+    record_every: u32,
+    /// file names TakeScreenshot() asked for, saved once the frame ends
+    screenshots: Vec<String>,
 }
 impl Default for RAY { fn default() -> Self { Self::new() }}
 impl RAY
@@ -25,16 +56,58 @@ impl RAY
     {
         Self
         {
+            #[cfg(feature = "raylib")]
             handler: RAYHandler {},
             is_drawing: false,
+            #[cfg(feature = "raylib")]
             rl:    None,
+            #[cfg(feature = "raylib")]
             thread: None,
+            headless: None,
+            canvas: None,
+            target_fps: 0,
+            frame: 0,
+            record_dir: None,
+            record_every: 0,
+            screenshots: Vec::new(),
         }
     }
 
+    /// has to be set before the guest opens its window
+    pub fn SetHeadless(&mut self, frames: u32)
+    {
+        self.headless = Some(frames);
+    }
+    pub fn is_headless(&self) -> bool
+    {
+        self.headless.is_some()
+    }
+    /// frames are named frame_000001.png on, counting from the first frame after OpenWindow()
+    pub fn RecordFrames(&mut self, dir: &str, every: u32) -> Result<(), Error>
+    {
+        if let Err(e) = std::fs::create_dir_all(dir)
+        {
+            return Err(error!("Couldnt create frame directory '{}': {}", dir, e));
+        }
+        self.record_dir = Some(PathBuf::from(dir));
+        self.record_every = every;
+        Ok(())
+    }
+
+    #[cfg(feature = "raylib")]
+    fn window_open(&self) -> bool
+    {
+        self.rl.is_some() && self.thread.is_some()
+    }
+    #[cfg(not(feature = "raylib"))]
+    fn window_open(&self) -> bool
+    {
+        false
+    }
+
     fn check_active(&self) -> Result<(), Error>
     {
-        if(self.rl.is_none() || self.thread.is_none())
+        if(self.canvas.is_none() && !self.window_open())
         {
             Err(error!("Raylib is not active!"))
         }
@@ -57,10 +130,22 @@ impl RAY
 
     pub fn OpenWindow(&mut self, w: u32, h: u32, title: String, resizable: bool) -> Result<(), Error>
     {
-        if(self.rl.is_some() || self.thread.is_some())
+        if(self.window_open() || self.canvas.is_some())
         {
             return Err(error!("Cannot initialize raylib while it is active!"));
         }
+        self.frame = 0;
+        if(self.headless.is_some())
+        {
+            self.canvas = Some(CANVAS::new(w, h));
+            return Ok(());
+        }
+        #[cfg(not(feature = "raylib"))]
+        {
+            let _ = (title, resizable);
+            Err(error!("Opening a window needs erebos_vm built with the raylib feature, use --ray-headless!"))
+        }
+        #[cfg(feature = "raylib")]
         if(resizable)
         {
             let (rl, thread) = raylib::init()
@@ -80,13 +165,18 @@ impl RAY
             self.rl     = Some(  rl  );
             self.thread = Some(thread);
         }
+        #[cfg(feature = "raylib")]
         Ok(())
     }
     pub fn CloseWindow(&mut self) -> Result<(), Error>
     {
         self.check_active()?;
-        self.rl = None;
-        self.thread = None;
+        #[cfg(feature = "raylib")]
+        {
+            self.rl = None;
+            self.thread = None;
+        }
+        self.canvas = None;
         Ok(())
     }
 
@@ -96,7 +186,11 @@ impl RAY
         {
             return Err(error!("Attempting to begin drawing while still owning draw handle!"));
         }
-        unsafe { raylib::ffi::BeginDrawing(); }
+        #[cfg(feature = "raylib")]
+        if(self.canvas.is_none())
+        {
+            unsafe { raylib::ffi::BeginDrawing(); }
+        }
         self.is_drawing = true;
         Ok(())
     }
@@ -106,32 +200,127 @@ impl RAY
         {
             return Err(error!("Cannot end drawing without draw handle!"));
         }
-        unsafe { raylib::ffi::EndDrawing(); }
+        // before the buffers get swapped, afterwards the frame is gone
+        self._save_frame()?;
+        #[cfg(feature = "raylib")]
+        if(self.canvas.is_none())
+        {
+            unsafe { raylib::ffi::EndDrawing(); }
+        }
         self.is_drawing = false;
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// saved into the frame directory [or the working directory without one] once the current frame ends
+    pub fn TakeScreenshot(&mut self, name: String) -> Result<(), Error>
+    {
+        self.check_active()?;
+        if(name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']))
+        {
+            return Err(error!("Screenshot name '{}' has to be a plain file name!", name));
+        }
+        self.screenshots.push(if(name.ends_with(".png")) { name } else { format!("{name}.png") });
+        Ok(())
+    }
+
+    /// (width, height, rgb) of what has been drawn so far
+    fn _capture(&mut self) -> Result<(u32, u32, Vec<u8>), Error>
+    {
+        if let Some(c) = &self.canvas
+        {
+            return Ok((c.w, c.h, c.pixels.clone()));
+        }
+        self.check_active()?;
+        #[cfg(feature = "raylib")]
+        {
+            // the last shapes may still be batched and not on the screen yet
+            unsafe { raylib::ffi::rlDrawRenderBatchActive(); }
+            let image = self.rl.as_mut().unwrap().load_image_from_screen(self.thread.as_ref().unwrap());
+            let rgb = image.get_image_data().iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+            Ok((image.width() as u32, image.height() as u32, rgb))
+        }
+        #[cfg(not(feature = "raylib"))]
+        unreachable!("only the canvas can be active without raylib")
+    }
+    fn _save_frame(&mut self) -> Result<(), Error>
+    {
+
+        let number = self.frame + 1;
+        let mut names = std::mem::take(&mut self.screenshots);
+        if(self.record_dir.is_some() && self.record_every != 0 && number.is_multiple_of(self.record_every))
+        {
+            names.push(format!("frame_{:06}.png", number));
+        }
+        if(names.is_empty()) { return Ok(()); }
+
+        let (w, h, rgb) = self._capture()?;
+        for name in names
+        {
+            let path = match &self.record_dir
+            {
+                Some(dir) => dir.join(name),
+                None => PathBuf::from(name),
+            };
+            png::write(&path, w, h, &rgb)?;
+        }
+
         Ok(())
+
     }
 
     pub fn ClearBackground(&mut self, color: Color) -> Result<(), Error>
     {
         self.check_drawing()?;
+        if let Some(c) = &mut self.canvas
+        {
+            c.Clear(RAY::_bytes(color));
+            return Ok(());
+        }
+        #[cfg(feature = "raylib")]
         self.handler.clear_background(color);
         Ok(())
     }
     pub fn DrawRectange(&mut self, x: u32, y: u32, w: u32, h: u32, color: Color) -> Result<(), Error>
     {
         self.check_drawing()?;
+        if let Some(c) = &mut self.canvas
+        {
+            c.FillRect(x, y, w, h, RAY::_bytes(color));
+            return Ok(());
+        }
+        #[cfg(feature = "raylib")]
         self.handler.draw_rectangle(x as i32, y as i32, w as i32, h as i32, color);
         Ok(())
     }
     pub fn DrawFPS(&mut self, x: u32, y: u32) -> Result<(), Error>
     {
         self.check_drawing()?;
+        if let Some(c) = &mut self.canvas
+        {
+            // the target fps, a headless frame takes no real time; colors as raylib picks them
+            let color = match self.target_fps
+            {
+                30.. => [0, 158, 47, 255],
+                15..30 => [255, 161, 0, 255],
+                _ => [230, 41, 55, 255],
+            };
+            c.Text(&format!("{:2} FPS", self.target_fps), x, y, 20, color);
+            return Ok(());
+        }
+        #[cfg(feature = "raylib")]
         self.handler.draw_fps(x as i32, y as i32);
         Ok(())
     }
     pub fn DrawText(&mut self, x: u32, y: u32, font_size: u32, color: Color, text: String) -> Result<(), Error>
     {
         self.check_drawing()?;
+        if let Some(c) = &mut self.canvas
+        {
+            c.Text(&text, x, y, font_size, RAY::_bytes(color));
+            return Ok(());
+        }
+        #[cfg(feature = "raylib")]
         self.handler.draw_text(text.as_str(), x as i32, y as i32, font_size as i32, color);
         Ok(())
     }
@@ -139,45 +328,69 @@ impl RAY
     pub fn SetTargetFPS(&mut self, fps: u32) -> Result<(), Error>
     {
         self.check_active()?;
-        self.rl.as_mut().unwrap().set_target_fps(fps);
+        self.target_fps = fps;
+        #[cfg(feature = "raylib")]
+        if let Some(rl) = &mut self.rl
+        {
+            rl.set_target_fps(fps);
+        }
         Ok(())
     }
     pub fn WindowShouldClose(&mut self) -> Result<u8, Error>
     {
         self.check_active()?;
-        if(self.rl.as_mut().unwrap().window_should_close())
+        if let Some(frames) = self.headless
         {
-            Ok(1)
+            return Ok((frames != 0 && self.frame >= frames) as u8);
         }
-        else
+        #[cfg(feature = "raylib")]
+        if(self.rl.as_mut().unwrap().window_should_close())
         {
-            Ok(0)
+            return Ok(1);
         }
+        Ok(0)
     }
 
     pub fn IsWindowResized(&self) -> Result<u8, Error>
     {
         self.check_active()?;
-        if(self.rl.as_ref().unwrap().is_window_resized())
+        if(self.canvas.is_some())
         {
-            Ok(1)
+            return Ok(0);
         }
-        else
+        #[cfg(feature = "raylib")]
+        if(self.rl.as_ref().unwrap().is_window_resized())
         {
-            Ok(0)
+            return Ok(1);
         }
+        Ok(0)
     }
     pub fn GetWindowWidth(&self) -> Result<u32, Error>
     {
         self.check_active()?;
-        Ok(self.rl.as_ref().unwrap().get_render_width() as u32)
+        if let Some(c) = &self.canvas
+        {
+            return Ok(c.w);
+        }
+        #[cfg(feature = "raylib")]
+        return Ok(self.rl.as_ref().unwrap().get_render_width() as u32);
+        #[cfg(not(feature = "raylib"))]
+        unreachable!("only the canvas can be active without raylib")
     }
     pub fn GetWindowHeight(&self) -> Result<u32, Error>
     {
         self.check_active()?;
-        Ok(self.rl.as_ref().unwrap().get_render_height() as u32)
+        if let Some(c) = &self.canvas
+        {
+            return Ok(c.h);
+        }
+        #[cfg(feature = "raylib")]
+        return Ok(self.rl.as_ref().unwrap().get_render_height() as u32);
+        #[cfg(not(feature = "raylib"))]
+        unreachable!("only the canvas can be active without raylib")
     }
 
     pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color { Color { r, g, b, a } }
+    fn _bytes(c: Color) -> [u8; 4] { [c.r, c.g, c.b, c.a] }
 
 }
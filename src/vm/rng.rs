@@ -0,0 +1,45 @@
+#![allow(unused_parens)]
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// xorshift64* generator, not cryptographic but fast and the same seed always gives the same numbers
+pub struct RNG
+{
+    state: u64,
+}
+impl Default for RNG { fn default() -> Self { Self::new() }}
+impl RNG
+{
+
+    /// seeded from the os
+    pub fn new() -> Self
+    {
+        Self::from_seed(RandomState::new().build_hasher().finish())
+    }
+    pub fn from_seed(seed: u64) -> Self
+    {
+        let mut r = Self { state: 0 };
+        r.seed(seed);
+        r
+    }
+
+    /// runs the seed through splitmix64, so small seeds like 0 or 1 still give a good state
+    pub fn seed(&mut self, seed: u64)
+    {
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        // xorshift never leaves 0
+        self.state = if(z == 0) { 0x9E3779B97F4A7C15 } else { z };
+    }
+
+    pub fn next_u32(&mut self) -> u32
+    {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 32) as u32
+    }
+
+}
@@ -1,23 +1,57 @@
 #![allow(unused_parens)]
+#[cfg(feature = "terminal")]
 use crossterm::event::{self, Event, KeyCode, KeyModifiers}; 
+#[cfg(feature = "terminal")]
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 
 use std::io::{stdout, Write};
 use std::ops::Div;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
+#[cfg(not(feature = "terminal"))]
+use std::sync::mpsc::{Receiver, TryRecvError};
 
 use _instruction_conversion::bytes_to_ins;
 use erebos::error;
 use erebos::instructions::{*, helpers::*};
-use crate::fs::FS;
+use crate::fs::{FS, FSResult};
+use crate::memfs::MEMFS;
 use crate::ray::RAY;
+use crate::rng::RNG;
+use crate::disk::{DISK, DISK_SECTOR_SIZE};
+use crate::net::{NET, NET_MAX_TRANSFER};
+use crate::channel::{CHANNEL, CHANNEL_MAX_MESSAGE, ChannelResult};
+#[cfg(feature = "terminal")]
+use crate::console::CONSOLE;
+use crate::display::{DISPLAY, DISPLAY_SIZE};
+use crate::audio::{AUDIO, AudioResult};
+use crate::perf::{PERF, PerfCounter};
+use crate::hooks::{HOOKS, MemoryAccess};
 
 
 
 const RAM_PAGE_SIZE : usize = 0x10000_usize;
 const RAM_PAGE_COUNT: usize = (0x100000000_usize.div_ceil(RAM_PAGE_SIZE));
 
+// instructions between two checks of the timer and keyboard
+const INTERRUPT_POLL_INTERVAL: u32 = 0x100;
+
+// loop turns between two checks whether the display should be redrawn
+const DISPLAY_POLL_INTERVAL: u32 = 0x1000;
+
+// max bytes a memcpy/memset moves per step, longer ones get re-executed so interrupts can land in between
+const MEM_BULK_CHUNK: u32 = 0x100;
+
+// most bytes one FillRandom() writes, the guest loops for more
+const RNG_MAX_FILL: u32 = 64 * 1024;
+
+// how long one instruction takes on the virtual clock of --deterministic [10 MIPS]
+const VIRTUAL_NS_PER_INSTRUCTION: u64 = 100;
+
+// where the argc/argv/env block of set_args() goes [physical], ra points here at entry
+pub const ARGS_BLOCK_ADR : u32 = 0xF000;
+pub const ARGS_BLOCK_SIZE: u32 = 0x0F00;
+
 #[allow(non_camel_case_types)]
 pub struct RAM
 {
@@ -103,6 +137,10 @@ pub enum InterruptID
     None = 0x00,
     UserModeViolation = 0x01,
     Syscall = 0x02,
+    Timer = 0x03,
+    Keyboard = 0x04,
+    Channel = 0x05,
+    DeviceUnavailable = 0x06,
 #[allow(non_camel_case_types)] __Err_Highest,
 }
 impl From<InterruptID> for u8
@@ -149,6 +187,8 @@ pub struct Interrupt
 {
     pub id: InterruptID,
     pub state: InterruptState,
+    /// entered through syscall, sysret switches back to the saved stack
+    pub kernel_stack: bool,
 }
 impl Interrupt
 {
@@ -158,6 +198,7 @@ impl Interrupt
         {
             id,
             state,
+            kernel_stack: false,
         }
     }
 }
@@ -168,10 +209,74 @@ pub struct InterruptState
     pub flags: u8,
     pub instruction_pointer: u32,
     pub stack_pointer: u32,
+    pub stack_position: u32,
+    pub stack_size: u32,
     pub user_mode: bool,
     pub  sub_mode: bool,
 }
 
+/// everything a core owns, the active core lives directly in the vm and gets swapped with these
+pub struct CoreState
+{
+    registers: [u32; 13],
+    flags: u8,
+    instruction_pointer: u32,
+    stack_pointer: u32,
+    stack_position: u32,
+    stack_size: u32,
+    user_mode: bool,
+     sub_mode: bool,
+    running: bool,
+    io_device: u16,
+    interrupt: Option<Interrupt>,
+    interrupt_location: u32,
+    handling_interrupt: bool,
+    waiting: bool,
+    timer_period: u32,
+    timer_next: Instant,
+    keyboard_interrupt: bool,
+    channel_interrupt: bool,
+    kernel_stack_position: u32,
+    kernel_stack_size: u32,
+    memory_maps: Vec<MemoryMap>,
+    memory_mapping_suspended: bool,
+}
+impl Default for CoreState
+{
+    fn default() -> Self { CoreState::new() }
+}
+impl CoreState
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            registers: [0; 13],
+            flags: 0,
+            instruction_pointer: 0,
+            stack_pointer: 0,
+            stack_position: 0,
+            stack_size: 0,
+            user_mode: false,
+             sub_mode: false,
+            running: false,
+            io_device: 0,
+            interrupt: None,
+            interrupt_location: 0,
+            handling_interrupt: false,
+            waiting: false,
+            timer_period: 0,
+            timer_next: Instant::now(),
+            keyboard_interrupt: false,
+            channel_interrupt: false,
+            kernel_stack_position: 0,
+            kernel_stack_size: 0,
+            memory_maps: Vec::new(),
+            memory_mapping_suspended: false,
+        }
+    }
+}
+
 pub struct VM
 {
 
@@ -181,6 +286,8 @@ pub struct VM
 
     code_section: Vec<u8>,
     section_mode: bool,
+    /// end of what load() put into ram, the args block has to stay clear of it
+    image_end: u32,
 
     user_mode: bool,
      sub_mode: bool,
@@ -194,19 +301,66 @@ pub struct VM
     stack_position: u32,
     stack_size: u32,
 
-    debug_print: bool,
 
     io_device: u16,
     fs: FS,
 
     interrupt: Option<Interrupt>,
     interrupt_location: u32,
+    /// set between an interrupt and its resolve, no timer/keyboard interrupts get delivered meanwhile
+    handling_interrupt: bool,
+    /// core sits in wfi
+    waiting: bool,
+    /// ms, 0 is off
+    timer_period: u32,
+    timer_next: Instant,
+    keyboard_interrupt: bool,
+    channel_interrupt: bool,
+    /// key that raised a keyboard interrupt, __in hands it out first
+    pending_key: Option<u8>,
+    #[cfg(not(feature = "terminal"))]
+    stdin_keys: Option<Receiver<Option<u8>>>,
+    interrupt_poll_counter: u32,
+    /// stack syscall switches to, size 0 is unset
+    kernel_stack_position: u32,
+    kernel_stack_size: u32,
 
     memory_maps: Vec<MemoryMap>,
     memory_mapping_suspended: bool,
 
     ray: RAY,
 
+    start_time: Instant,
+    /// executed instructions over all cores
+    instruction_count: u64,
+    /// clock reads the instruction count instead of the host time
+    deterministic: bool,
+    /// time skipped by Sleep() while deterministic
+    virtual_sleep_ns: u64,
+    /// run() stops once instruction_count reaches it, 0 for no limit
+    instruction_budget: u64,
+    budget_exhausted: bool,
+    /// status given to Exit(), None if the guest stopped some other way
+    exit_status: Option<u32>,
+
+    rng: RNG,
+    disk: DISK,
+    net: NET,
+    channel: CHANNEL,
+    #[cfg(feature = "terminal")]
+    console: CONSOLE,
+    display: Option<DISPLAY>,
+    display_poll_counter: u32,
+    audio: AUDIO,
+    perf: PERF,
+    hooks: HOOKS,
+
+    /// slot of the active core is a placeholder
+    cores: Vec<CoreState>,
+    core_id: usize,
+    core_slice: u32,
+    core_slice_left: u32,
+
 }
 impl Default for VM
 {
@@ -226,6 +380,7 @@ impl VM
             
             code_section: Vec::new(),
             section_mode: false,
+            image_end: 0,
 
             user_mode: false,
              sub_mode: false,
@@ -238,19 +393,55 @@ impl VM
             running: false,
             flags: 0,
             
-            debug_print: false,
 
             io_device: 0,
             fs: FS::new(),
 
             interrupt: None,
             interrupt_location: 0,
+            handling_interrupt: false,
+            waiting: false,
+            timer_period: 0,
+            timer_next: Instant::now(),
+            keyboard_interrupt: false,
+            channel_interrupt: false,
+            pending_key: None,
+            #[cfg(not(feature = "terminal"))]
+            stdin_keys: None,
+            interrupt_poll_counter: 0,
+            kernel_stack_position: 0,
+            kernel_stack_size: 0,
 
             memory_maps: Vec::new(),
             memory_mapping_suspended: false,
 
             ray: RAY::new(),
 
+            start_time: Instant::now(),
+            instruction_count: 0,
+            deterministic: false,
+            virtual_sleep_ns: 0,
+            instruction_budget: 0,
+            budget_exhausted: false,
+            exit_status: None,
+
+            rng: RNG::new(),
+            disk: DISK::new(),
+            net: NET::new(),
+            channel: CHANNEL::new(),
+            #[cfg(feature = "terminal")]
+            console: CONSOLE::new(),
+            display: None,
+            display_poll_counter: 0,
+            audio: AUDIO::new(),
+            perf: PERF::new(),
+            hooks: HOOKS::new(),
+
+            cores: vec![CoreState::new()],
+            core_id: 0,
+            core_slice: 100,
+            core_slice_left: 100,
+
         }
 
     }
@@ -324,6 +515,7 @@ impl VM
         else
         {
             self.memory.set(self.stack_pointer, v);
+            self._memory_write(self.stack_pointer, self.stack_pointer, 1, v as u32);
             self.stack_pointer += 1;
             Ok(())
         }
@@ -373,7 +565,9 @@ impl VM
         else
         {
             self.stack_pointer -= 1;
-            Ok(self.memory.get(self.stack_pointer))
+            let v = self.memory.get(self.stack_pointer);
+            self._memory_read(self.stack_pointer, self.stack_pointer, 1, v as u32);
+            Ok(v)
         }
     }
     fn stack_pop16(&mut self) -> Result<u16, Error>
@@ -388,7 +582,9 @@ impl VM
             let a = self.memory.get(self.stack_pointer);
             self.stack_pointer -= 1;
             let b = self.memory.get(self.stack_pointer);
-            Ok(u8_2_u16((b, a)))
+            let v = u8_2_u16((b, a));
+            self._memory_read(self.stack_pointer, self.stack_pointer, 2, v as u32);
+            Ok(v)
         }
     }
     fn stack_pop32(&mut self) -> Result<u32, Error>
@@ -407,16 +603,33 @@ impl VM
             let c = self.memory.get(self.stack_pointer);
             self.stack_pointer -= 1;
             let d = self.memory.get(self.stack_pointer);
-            Ok(u8_2_u32((d, c, b, a)))
+            let v = u8_2_u32((d, c, b, a));
+            self._memory_read(self.stack_pointer, self.stack_pointer, 4, v);
+            Ok(v)
         }
     }
 
+    /// every guest load and store ends up here, for the performance counters and the hooks
+    fn _memory_read(&self, virtual_adr: u32, physical_adr: u32, size: u8, value: u32)
+    {
+        self.perf.Count(PerfCounter::MemoryReads, size as u64);
+        self.hooks.MemoryRead(self.core_id, MemoryAccess { virtual_adr, physical_adr, size, value });
+    }
+    fn _memory_write(&self, virtual_adr: u32, physical_adr: u32, size: u8, value: u32)
+    {
+        self.perf.Count(PerfCounter::MemoryWrites, size as u64);
+        self.hooks.MemoryWrite(self.core_id, MemoryAccess { virtual_adr, physical_adr, size, value });
+    }
+
     pub fn memset(&mut self, adr: u32, v: u8) -> Result<(), Error>
     {
-        self.memory.set(self.mem_map(adr), v);
+        let phys = self.mem_map(adr);
+        self.memory.set(phys, v);
+        self._memory_write(adr, phys, 1, v as u32);
         Ok(())
     }
-    fn memset16(&mut self, adr: u32, v: u16) -> Result<(), Error>
+    /// (physical address, value), without reporting the access
+    fn _memset16(&mut self, adr: u32, v: u16) -> Result<u32, Error>
     {
         let adr = self.mem_map(adr);
         if(adr as u64 >= 0xFFFF)
@@ -426,24 +639,42 @@ impl VM
         let v = u16_2_u8(v);
         self.memory.set(adr    , v.0);
         self.memory.set(adr + 1, v.1);
+        Ok(adr)
+    }
+    fn memset16(&mut self, adr: u32, v: u16) -> Result<(), Error>
+    {
+        let phys = self._memset16(adr, v)?;
+        self._memory_write(adr, phys, 2, v as u32);
         Ok(())
     }
     fn memset32(&mut self, adr: u32, v: u32) -> Result<(), Error>
     {
-        let adr = self.mem_map(adr);
-        if(adr as u64 >= 0xFFFF)
+        let phys = self.mem_map(adr);
+        if(phys as u64 >= 0xFFFF)
         {
-            println!("{adr:#010x}");
+            println!("{phys:#010x}");
             return Err(error!("Cannot memset outside of ram range!"))
         }
-        let v = u32_2_u16(v);
-        self.memset16(adr    , v.0)?;
-        self.memset16(adr + 2, v.1)?;
+        let h = u32_2_u16(v);
+        self._memset16(phys    , h.0)?;
+        self._memset16(phys + 2, h.1)?;
+        self._memory_write(adr, phys, 4, v);
         Ok(())
     }
     pub fn memget(&mut self, adr: u32) -> Result<u8, Error>
-    { Ok(self.memory.get(self.mem_map(adr))) }
-    fn memget16(&mut self, adr: u32) -> Result<u16, Error>
+    {
+        let phys = self.mem_map(adr);
+        let v = self.memory.get(phys);
+        self._memory_read(adr, phys, 1, v as u32);
+        Ok(v)
+    }
+    /// reads a byte through the memory maps without counting or reporting it, for looking at the vm from outside
+    pub fn peek(&self, adr: u32) -> u8
+    {
+        self.memory.get_safe(self.mem_map(adr))
+    }
+    /// (physical address, value), without reporting the access
+    fn _memget16(&mut self, adr: u32) -> Result<(u32, u16), Error>
     { 
         let adr = self.mem_map(adr);
         if(adr as u64 >= 0xFFFF)
@@ -452,31 +683,41 @@ impl VM
         }
         let a = self.memory.get(adr    );
         let b = self.memory.get(adr + 1);
-        Ok(u8_2_u16((a, b)))
+        Ok((adr, u8_2_u16((a, b))))
+    }
+    fn memget16(&mut self, adr: u32) -> Result<u16, Error>
+    {
+        let (phys, v) = self._memget16(adr)?;
+        self._memory_read(adr, phys, 2, v as u32);
+        Ok(v)
     }
     fn memget32(&mut self, adr: u32) -> Result<u32, Error>
     { 
-        let adr = self.mem_map(adr);
-        if(adr as u64 >= 0xFFFF)
+        let phys = self.mem_map(adr);
+        if(phys as u64 >= 0xFFFF)
         {
             return Err(error!("Cannot memget outside of ram range!"))
         }
-        let a = self.memget16(adr    )?;
-        let b = self.memget16(adr + 2)?;
-        Ok(u16_2_u32((a, b)))
+        let a = self._memget16(phys    )?.1;
+        let b = self._memget16(phys + 2)?.1;
+        let v = u16_2_u32((a, b));
+        self._memory_read(adr, phys, 4, v);
+        Ok(v)
     }
     fn memget32_safe(&self, adr: u32) -> Result<u32, Error>
     { 
-        let adr = self.mem_map(adr);
-        if(adr as u64 >= 0xFFFF)
+        let phys = self.mem_map(adr);
+        if(phys as u64 >= 0xFFFF)
         {
             return Err(error!("Cannot memget outside of ram range!"))
         }
-        let a = self.memory.get_safe(adr    );
-        let b = self.memory.get_safe(adr + 1);
-        let c = self.memory.get_safe(adr + 2);
-        let d = self.memory.get_safe(adr + 3);
-        Ok(u8_2_u32((a, b, c, d)))
+        let a = self.memory.get_safe(phys    );
+        let b = self.memory.get_safe(phys + 1);
+        let c = self.memory.get_safe(phys + 2);
+        let d = self.memory.get_safe(phys + 3);
+        let v = u8_2_u32((a, b, c, d));
+        self._memory_read(adr, phys, 4, v);
+        Ok(v)
     }
 
     fn set_flag(&mut self, flag: u8, status: bool) -> Result<(), Error>
@@ -499,6 +740,7 @@ impl VM
         {
             _IRALUInstruction2::NOT(m) => m,
             _IRALUInstruction2::CMP(m) => m,
+            _IRALUInstruction2::FCMP(m) => m,
         };
 
         let left = match &m.0
@@ -535,6 +777,32 @@ impl VM
                 return Ok(());
 
             },
+            _IRALUInstruction2::FCMP(_) =>
+            {
+
+                let right = match &m.1
+                {
+                    IRInstructionModifier::Register (r) => self.get_reg  (*r),
+                    IRInstructionModifier::Memory   (a) => self.memget32 (*a)?,
+                    _ => return Err(error!("INVALID FCMP ARGUMENT {:?}", m)),
+                };
+
+                self.set_flag(FLAG_E, false)?;
+                self.set_flag(FLAG_A, false)?;
+                self.set_flag(FLAG_B, false)?;
+
+                // unordered (NaN) comparisons leave every flag cleared
+                match f32::from_bits(left).partial_cmp(&f32::from_bits(right))
+                {
+                    Some(std::cmp::Ordering::Equal  ) => self.set_flag(FLAG_E, true)?,
+                    Some(std::cmp::Ordering::Less   ) => self.set_flag(FLAG_B, true)?,
+                    Some(std::cmp::Ordering::Greater) => self.set_flag(FLAG_A, true)?,
+                    None => {},
+                }
+
+                return Ok(());
+
+            },
         };
 
         match &m.1
@@ -564,6 +832,10 @@ impl VM
             _IRALUInstruction3:: SHR(m) => m,
             _IRALUInstruction3::NAND(m) => m,
             _IRALUInstruction3:: NOR(m) => m,
+            _IRALUInstruction3::FADD(m) => m,
+            _IRALUInstruction3::FSUB(m) => m,
+            _IRALUInstruction3::FMUL(m) => m,
+            _IRALUInstruction3::FDIV(m) => m,
         };
 
         let r = match &m
@@ -608,6 +880,10 @@ impl VM
             _IRALUInstruction3:: SHR(_) => l.overflowing_shr(r).0,
             _IRALUInstruction3::NAND(_) => !(l & r),
             _IRALUInstruction3:: NOR(_) => !(l | r),
+            _IRALUInstruction3::FADD(_) => (f32::from_bits(l) + f32::from_bits(r)).to_bits(),
+            _IRALUInstruction3::FSUB(_) => (f32::from_bits(l) - f32::from_bits(r)).to_bits(),
+            _IRALUInstruction3::FMUL(_) => (f32::from_bits(l) * f32::from_bits(r)).to_bits(),
+            _IRALUInstruction3::FDIV(_) => (f32::from_bits(l) / f32::from_bits(r)).to_bits(),
         };
 
         match &m
@@ -642,7 +918,43 @@ impl VM
         match ins
         {
             IRInstruction::NOP | IRInstruction::DATA(_) => {},
-            IRInstruction::HLT => if(self.validate_kernel_mode(true)?) { self.running = false },
+            IRInstruction::HLT => if(self.validate_kernel_mode(true)?)
+            {
+                self.running = false;
+                self.hooks.Halt(self.core_id, self.instruction_pointer);
+            },
+            IRInstruction::WFI => if(self.validate_kernel_mode(true)?)
+            {
+                if(!self.interrupt_sources_enabled())
+                {
+                    return Err(error!("wfi without timer, keyboard or channel interrupts enabled would wait forever!"));
+                }
+                if(self.keyboard_interrupt)
+                {
+                    // stays raw while waiting, otherwise the terminal echoes and line buffers the keys
+                    VM::set_raw_mode(true);
+                }
+                self.waiting = true;
+            },
+            IRInstruction::SYSCALL =>
+            {
+                if(self.kernel_stack_size == 0)
+                {
+                    return Err(error!("syscall without a kernel stack, set one with SetKernelStack()!"));
+                }
+                self.send_interrupt(InterruptID::Syscall)?;
+                self.stack_position = self.kernel_stack_position;
+                self.stack_size     = self.kernel_stack_size;
+                self.stack_pointer  = self.kernel_stack_position;
+                if let Some(int) = &mut self.interrupt
+                {
+                    int.kernel_stack = true;
+                }
+            },
+            IRInstruction::SYSRET => if(self.validate_kernel_mode(true)?)
+            {
+                self.sysret()?;
+            },
             IRInstruction::CLF => self.flags = 0,
             IRInstruction::PSHFLG => self.stack_push(self.flags)?,
             IRInstruction::POPFLG => self.flags = self.stack_pop()?,
@@ -668,6 +980,90 @@ impl VM
                 self.set_reg(v, r);
             },
 
+            IRInstruction::ITOF(r) =>
+            {
+                let v = self.get_reg(r) as i32 as f32;
+                self.set_reg(v.to_bits(), r);
+            },
+            IRInstruction::FTOI(r) =>
+            {
+                let v = f32::from_bits(self.get_reg(r)) as i32;
+                self.set_reg(v as u32, r);
+            },
+            IRInstruction::FSQRT(r) =>
+            {
+                let v = f32::from_bits(self.get_reg(r)).sqrt();
+                self.set_reg(v.to_bits(), r);
+            },
+
+            IRInstruction::MEMCPY(rd, rs, rl) =>
+            {
+                let dst = self.get_reg(rd);
+                let src = self.get_reg(rs);
+                let len = self.get_reg(rl);
+                let n = len.min(MEM_BULK_CHUNK);
+                for i in 0..n
+                {
+                    let b = self.memget(src.wrapping_add(i))?;
+                    self.memset(dst.wrapping_add(i), b)?;
+                }
+                self.set_reg(dst.wrapping_add(n), rd);
+                self.set_reg(src.wrapping_add(n), rs);
+                self.set_reg(len - n, rl);
+                if(len - n > 0)
+                {
+                    self.instruction_pointer -= 3; // not done yet, run again
+                }
+            },
+            IRInstruction::MEMSET(rd, rv, rl) =>
+            {
+                let dst = self.get_reg(rd);
+                let val = self.get_reg(rv) as u8;
+                let len = self.get_reg(rl);
+                let n = len.min(MEM_BULK_CHUNK);
+                for i in 0..n
+                {
+                    self.memset(dst.wrapping_add(i), val)?;
+                }
+                self.set_reg(dst.wrapping_add(n), rd);
+                self.set_reg(len - n, rl);
+                if(len - n > 0)
+                {
+                    self.instruction_pointer -= 3; // not done yet, run again
+                }
+            },
+
+            // one instruction never gets split by an interrupt, so these are indivisible by construction
+            IRInstruction::XCHG(rm, rv) =>
+            {
+                let adr = self.get_reg(rm);
+                let old = self.memget32(adr)?;
+                self.memset32(adr, self.get_reg(rv))?;
+                self.set_reg(old, rv);
+            },
+            IRInstruction::CAS(rm, re, rn) =>
+            {
+                let adr = self.get_reg(rm);
+                let old = self.memget32(adr)?;
+                let equ = old == self.get_reg(re);
+                if(equ)
+                {
+                    self.memset32(adr, self.get_reg(rn))?;
+                }
+                else
+                {
+                    self.set_reg(old, re);
+                }
+                self.set_flag(FLAG_E, equ)?;
+            },
+            IRInstruction::XADD(rm, rv) =>
+            {
+                let adr = self.get_reg(rm);
+                let old = self.memget32(adr)?;
+                self.memset32(adr, old.overflowing_add(self.get_reg(rv)).0)?;
+                self.set_reg(old, rv);
+            },
+
             IRInstruction::DBG => 
             {
                 let mut msg = String::new();
@@ -732,52 +1128,17 @@ impl VM
                 if(self.validate_kernel_mode(false)?)
                 {
 
-                    enable_raw_mode().unwrap();
-
-                    while let Ok(true) = event::poll(Duration::from_millis(1)) 
+                    if let Some(c) = self.pending_key.take()
                     {
-                        _ = event::read();
+                        self.set_reg(c as u32, r);
+                        return Ok(());
                     }
 
-                    let c = loop 
-                    {
-                        if let Event::Key(k) = event::read().unwrap()
-                        {
-                            let ctrl = matches!(k.modifiers, KeyModifiers::CONTROL);
-                            match k.code
-                            {
-                                KeyCode::Enter => break '\n',
-                                KeyCode::Backspace => break '\x08',
-                                KeyCode::Char('c') =>
-                                {
-                                    if(ctrl)
-                                    {
-                                        self.running = false;
-                                        return Ok(());
-                                    }
-                                },
-                                KeyCode::Esc =>
-                                {
-                                    //self.running = false;
-                                    //return Ok(());
-                                },
-                                _ => {},
-                            }
-                            let Some(c) = k.code.as_char()
-                            else { continue; };
-                            break c;
-                        }
-                    } as u8;
-
-                    while let Ok(true) = event::poll(Duration::from_millis(1)) 
+                    if let Some(c) = self.read_key()
                     {
-                        _ = event::read();
+                        self.set_reg(c as u32, r);
                     }
 
-                    disable_raw_mode().unwrap();
-
-                    self.set_reg(c as u32, r);
-
                 }
 
             },
@@ -959,6 +1320,22 @@ impl VM
             },
             IRInstruction::RET => self.instruction_pointer = self.stack_pop32()?,
 
+            // rip already holds a physical address, so no mapping here
+            IRInstruction::JMPREL(o) => self.instruction_pointer = self.instruction_pointer.wrapping_add(o),
+            IRInstruction::JIFREL(o, f) =>
+            {
+                if((self.flags & f) != 0)
+                {
+                    self.instruction_pointer = self.instruction_pointer.wrapping_add(o);
+                }
+            },
+            IRInstruction::CALREL(o) =>
+            {
+                self.stack_push32(self.instruction_pointer)?;
+                self.instruction_pointer = self.instruction_pointer.wrapping_add(o);
+            },
+            IRInstruction::LEAREL(o, r) => self.set_reg(self.instruction_pointer.wrapping_add(o), r),
+
             IRInstruction::ALU(ins) => self.execute_alu_instruction(ins)?,
         };
 
@@ -969,11 +1346,13 @@ impl VM
     fn execute_next_instruction(&mut self) -> Result<(), Error>
     {
 
-        if(self.debug_print) { print!("[{:#010x}]", self.instruction_pointer); }
-
+        let ip = self.instruction_pointer;
         let ins = bytes_to_ins(|| self.fetch_byte())?;
 
-        if(self.debug_print) { println!(" Executing {:?}", ins); }
+        self.hooks.Instruction(self.core_id, ip, &ins);
+
+        self.instruction_count += 1;
+        self.perf.Count(PerfCounter::Instructions, 1);
 
         self.execute_instruction(ins)
 
@@ -983,111 +1362,572 @@ impl VM
     {
 
         self.running = true;
+        self.start_time = Instant::now();
 
-        while(self.running)
+        while(self.running || self.cores.iter().any(|c| c.running))
         {
-            self.execute_next_instruction()?
-        }
-
-        Ok(())
 
-    }
-
-
-
-    pub fn load(&mut self, data: Vec<u8>, pos: u32) -> Result<(), Error>
-    {
-
-        if(self.section_mode)
-        {
+            if(self.instruction_budget != 0 && self.instruction_count >= self.instruction_budget)
+            {
+                self.budget_exhausted = true;
+                break;
+            }
 
-            if((data.len() + (pos as usize)) > 0x10000)
+            if(self.running)
             {
-                return Err(error!("Cannot load data at addresses higher that {:#x}! (attempting to load {:#x} bytes with offset {:#x})", 0x10000, data.len(), pos));
+                if(self.waiting)
+                {
+                    self.wait_for_interrupt()?;
+                }
+                else
+                {
+                    self.execute_next_instruction()?;
+                    if(self.interrupt_sources_enabled())
+                    {
+                        self.interrupt_poll_counter += 1;
+                        if(self.interrupt_poll_counter >= INTERRUPT_POLL_INTERVAL)
+                        {
+                            self.interrupt_poll_counter = 0;
+                            self.deliver_pending_interrupt()?;
+                        }
+                    }
+                }
             }
-    
-            self.code_section = data;
-    
-        }
-        else
-        {
 
-            if((data.len() + (pos as usize)) > 0x10000)
+            if(self.cores.len() > 1)
             {
-                return Err(error!("Loading {} into ram at pos {:#x} overflows ram!", data.len(), pos));
+                self.core_slice_left = self.core_slice_left.saturating_sub(1);
+                if(self.core_slice_left == 0 || !self.running || self.waiting)
+                {
+                    self.schedule_next_core();
+                }
             }
 
-            for (i, item) in data.iter().enumerate()
-            { self.memset(pos + (i as u32), *item)?; }
+            if(self.display.is_some())
+            {
+                self.display_poll_counter += 1;
+                if(self.display_poll_counter >= DISPLAY_POLL_INTERVAL)
+                {
+                    self.display_poll_counter = 0;
+                    self.refresh_display(false)?;
+                }
+            }
 
         }
 
+        self.switch_core(0);
+        self.refresh_display(true)?;
+
         Ok(())
 
     }
 
-    pub fn load_executable(&mut self, exe: Vec<u8>) -> Result<(), Error>
+    /// cells of the display as they are in ram [physical, no memory maps]
+    fn display_cells(&self) -> Option<Vec<u8>>
     {
+        let adr = self.display.as_ref()?.adr;
+        Some((0..DISPLAY_SIZE).map(|i| self.memory.get_safe(adr + i)).collect())
+    }
+    fn refresh_display(&mut self, force: bool) -> Result<(), Error>
+    {
+        match &self.display
+        {
+            Some(d) if force || d.ShouldDraw() => {},
+            _ => return Ok(()),
+        }
+        let Some(cells) = self.display_cells()
+        else { return Ok(()); };
+        match &mut self.display
+        {
+            Some(d) => d.Draw(cells),
+            None => Ok(()),
+        }
+    }
 
-        if(exe.len() < 32)
+    /// blocks till a key is pressed, None if the vm got stopped meanwhile
+    #[cfg(feature = "terminal")]
+    fn read_key(&mut self) -> Option<u8>
+    {
+
+        enable_raw_mode().unwrap();
+
+        while let Ok(true) = event::poll(Duration::from_millis(1)) 
         {
-            return Err(error!("Executable cannot have less than 32 bytes!"));
+            _ = event::read();
         }
 
-        let mut data: Vec<u8> = Vec::new();
-        let mut header: [u8; 32] = [0; 32];
-        
-        for i in 0..exe.len()
+        let c = loop 
         {
-            if(i < 32)
+            if let Some(c) = self.key_to_byte(event::read().unwrap())
             {
-                header[i] = exe[i];
+                break c;
             }
-            else
+            if(!self.running)
             {
-                data.push(exe[i]);
+                return None;
             }
+        };
+
+        while let Ok(true) = event::poll(Duration::from_millis(1)) 
+        {
+            _ = event::read();
         }
 
-        let header = IRBinaryHeader::deserialize(header);
+        disable_raw_mode().unwrap();
 
-        match header
+        Some(c)
+
+    }
+    /// without the terminal stdin cant be polled, so a thread reads it as it comes [line buffered] and hands the bytes over
+    /// -> None once the input ended
+    #[cfg(not(feature = "terminal"))]
+    fn stdin_keys(&mut self) -> &Receiver<Option<u8>>
+    {
+        self.stdin_keys.get_or_insert_with(||
         {
-            IRBinaryHeader::V_0000(v0000) =>
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move ||
             {
-                self.load(data, 0)?;
-                self.stack_position = v0000.stack_adr;
-                self.stack_pointer  = v0000.stack_adr;
-                self.stack_size     = v0000.stack_size;
-                self.instruction_pointer = v0000.entry_point;
-                //TODO: smth with the flags lol
+                use std::io::Read;
+                let mut c = [0u8; 1];
+                loop
+                {
+                    let key = match std::io::stdin().read(&mut c)
+                    {
+                        Ok(1) => Some(c[0]),
+                        _ => None,
+                    };
+                    if(tx.send(key).is_err() || key.is_none()) { break; }
+                }
+            });
+            rx
+        })
+    }
+    /// end of input stops the vm
+    #[cfg(not(feature = "terminal"))]
+    fn read_key(&mut self) -> Option<u8>
+    {
+        match self.stdin_keys().recv()
+        {
+            Ok(Some(c)) => Some(c),
+            _ =>
+            {
+                self.running = false;
+                None
             },
         }
+    }
 
-        if(self.stack_position < 0x100)
-        {
-            self.stack_position = 0x100;
-            self.stack_pointer  = 0x100;
-        }
+    #[cfg(feature = "terminal")]
+    fn set_raw_mode(on: bool)
+    {
+        if(on) { _ = enable_raw_mode(); }
+        else   { _ = disable_raw_mode(); }
+    }
+    #[cfg(not(feature = "terminal"))]
+    fn set_raw_mode(_on: bool) {}
 
-        if(self.stack_size < 512)
+    /// turns a key press into the byte __in hands out, ctrl+c stops the vm
+    #[cfg(feature = "terminal")]
+    fn key_to_byte(&mut self, e: Event) -> Option<u8>
+    {
+        let Event::Key(k) = e
+        else { return None; };
+        match k.code
         {
-            self.stack_size = 512;
+            KeyCode::Enter => return Some(b'\n'),
+            KeyCode::Backspace => return Some(0x08),
+            KeyCode::Char('c') if matches!(k.modifiers, KeyModifiers::CONTROL) =>
+            {
+                self.running = false;
+                return None;
+            },
+            _ => {},
         }
-        
-        Ok(())
-
+        k.code.as_char().map(|c| c as u8)
     }
 
-    pub fn enable_debug_print (&mut self) { self.debug_print  = true; }
-    pub fn enable_section_mode(&mut self) { self.section_mode = true; }
-
-
+    /// whether a key is pending, takes one from the terminal if there isnt yet
+    #[cfg(feature = "terminal")]
+    fn poll_keyboard(&mut self) -> bool
+    {
+        if(self.pending_key.is_none() && (self.waiting || enable_raw_mode().is_ok()))
+        {
+            while let Ok(true) = event::poll(Duration::ZERO)
+            {
+                let Ok(e) = event::read()
+                else { break; };
+                if let Some(c) = self.key_to_byte(e)
+                {
+                    self.pending_key = Some(c);
+                    break;
+                }
+            }
+            if(!self.waiting)
+            {
+                _ = disable_raw_mode();
+            }
+        }
+        self.pending_key.is_some()
+    }
+    #[cfg(not(feature = "terminal"))]
+    fn poll_keyboard(&mut self) -> bool
+    {
+        if(self.pending_key.is_none())
+        {
+            match self.stdin_keys().try_recv()
+            {
+                Ok(Some(c)) => self.pending_key = Some(c),
+                Err(TryRecvError::Empty) => {},
+                // the input ended, that only stops the vm once it waits for a key [like __in]
+                _ => if(self.waiting) { self.running = false; },
+            }
+        }
+        self.pending_key.is_some()
+    }
+
+    fn interrupt_sources_enabled(&self) -> bool
+    {
+        self.timer_period != 0 || self.keyboard_interrupt || self.channel_interrupt
+    }
+    fn poll_interrupt_sources(&mut self) -> Option<InterruptID>
+    {
+
+        if(self.timer_period != 0 && Instant::now() >= self.timer_next)
+        {
+            return Some(InterruptID::Timer);
+        }
+
+        if(self.keyboard_interrupt && self.poll_keyboard())
+        {
+            return Some(InterruptID::Keyboard);
+        }
+
+        // stays pending till the message is received
+        if(self.channel_interrupt && self.channel.HasMessage())
+        {
+            return Some(InterruptID::Channel);
+        }
+
+        None
+
+    }
+    /// returns whether an interrupt is pending, it only gets delivered if none is being handled right now
+    fn deliver_pending_interrupt(&mut self) -> Result<bool, Error>
+    {
+
+        let Some(id) = self.poll_interrupt_sources()
+        else { return Ok(false); };
+
+        if(!self.handling_interrupt)
+        {
+            if(id == InterruptID::Timer)
+            {
+                self.timer_next = Instant::now() + Duration::from_millis(self.timer_period as u64);
+            }
+            self.send_interrupt(id)?;
+        }
+
+        Ok(true)
+
+    }
+    fn wait_for_interrupt(&mut self) -> Result<(), Error>
+    {
+
+        if(self.deliver_pending_interrupt()? || !self.running)
+        {
+            if(self.keyboard_interrupt)
+            {
+                VM::set_raw_mode(false);
+            }
+            self.waiting = false;
+            return Ok(());
+        }
+
+        // only sleep once every core idles
+        if(self.cores.iter().all(|c| !c.running || c.waiting))
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        Ok(())
+
+    }
+
+    /// round robin, stays on the current core if no other one is running
+    fn schedule_next_core(&mut self)
+    {
+        self.core_slice_left = self.core_slice;
+        let count = self.cores.len();
+        for i in 1..count
+        {
+            let id = (self.core_id + i) % count;
+            if(self.cores[id].running)
+            {
+                self.switch_core(id);
+                return;
+            }
+        }
+    }
+    fn switch_core(&mut self, id: usize)
+    {
+        if(id == self.core_id) { return; }
+        let next = std::mem::take(&mut self.cores[id]);
+        let curr = self.swap_core_state(next);
+        self.cores[self.core_id] = curr;
+        self.core_id = id;
+    }
+    fn swap_core_state(&mut self, mut core: CoreState) -> CoreState
+    {
+        std::mem::swap(&mut self.registers, &mut core.registers);
+        std::mem::swap(&mut self.flags, &mut core.flags);
+        std::mem::swap(&mut self.instruction_pointer, &mut core.instruction_pointer);
+        std::mem::swap(&mut self.stack_pointer, &mut core.stack_pointer);
+        std::mem::swap(&mut self.stack_position, &mut core.stack_position);
+        std::mem::swap(&mut self.stack_size, &mut core.stack_size);
+        std::mem::swap(&mut self.user_mode, &mut core.user_mode);
+        std::mem::swap(&mut self. sub_mode, &mut core. sub_mode);
+        std::mem::swap(&mut self.running, &mut core.running);
+        std::mem::swap(&mut self.io_device, &mut core.io_device);
+        std::mem::swap(&mut self.interrupt, &mut core.interrupt);
+        std::mem::swap(&mut self.interrupt_location, &mut core.interrupt_location);
+        std::mem::swap(&mut self.handling_interrupt, &mut core.handling_interrupt);
+        std::mem::swap(&mut self.waiting, &mut core.waiting);
+        std::mem::swap(&mut self.timer_period, &mut core.timer_period);
+        std::mem::swap(&mut self.timer_next, &mut core.timer_next);
+        std::mem::swap(&mut self.keyboard_interrupt, &mut core.keyboard_interrupt);
+        std::mem::swap(&mut self.channel_interrupt, &mut core.channel_interrupt);
+        std::mem::swap(&mut self.kernel_stack_position, &mut core.kernel_stack_position);
+        std::mem::swap(&mut self.kernel_stack_size, &mut core.kernel_stack_size);
+        std::mem::swap(&mut self.memory_maps, &mut core.memory_maps);
+        std::mem::swap(&mut self.memory_mapping_suspended, &mut core.memory_mapping_suspended);
+        core
+    }
+
+
+
+    pub fn load(&mut self, data: Vec<u8>, pos: u32) -> Result<(), Error>
+    {
+
+        if(self.section_mode)
+        {
+
+            if((data.len() + (pos as usize)) > 0x10000)
+            {
+                return Err(error!("Cannot load data at addresses higher that {:#x}! (attempting to load {:#x} bytes with offset {:#x})", 0x10000, data.len(), pos));
+            }
+    
+            self.code_section = data;
+    
+        }
+        else
+        {
+
+            if((data.len() + (pos as usize)) > 0x10000)
+            {
+                return Err(error!("Loading {} into ram at pos {:#x} overflows ram!", data.len(), pos));
+            }
+
+            for (i, item) in data.iter().enumerate()
+            { self.memset(pos + (i as u32), *item)?; }
+            self.image_end = self.image_end.max(pos + data.len() as u32);
+
+        }
+
+        Ok(())
+
+    }
+
+    pub fn load_executable(&mut self, exe: Vec<u8>) -> Result<(), Error>
+    {
+
+        if(exe.len() < 32)
+        {
+            return Err(error!("Executable cannot have less than 32 bytes!"));
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        let mut header: [u8; 32] = [0; 32];
+        
+        for i in 0..exe.len()
+        {
+            if(i < 32)
+            {
+                header[i] = exe[i];
+            }
+            else
+            {
+                data.push(exe[i]);
+            }
+        }
+
+        let header = IRBinaryHeader::deserialize(header);
+
+        match header
+        {
+            IRBinaryHeader::V_0000(v0000) =>
+            {
+                self.load(data, 0)?;
+                self.stack_position = v0000.stack_adr;
+                self.stack_pointer  = v0000.stack_adr;
+                self.stack_size     = v0000.stack_size;
+                self.instruction_pointer = v0000.entry_point;
+                //TODO: smth with the flags lol
+            },
+            IRBinaryHeader::V_0001(v0001) =>
+            {
+                self.load(data, 0)?;
+                self.stack_position = v0001.stack_adr;
+                self.stack_pointer  = v0001.stack_adr;
+                self.stack_size     = v0001.stack_size;
+                self.instruction_pointer = v0001.entry_point;
+                if(!v0001.file_loc.is_empty())
+                {
+                    // same sandbox as SetRoot() from the guest
+                    let result = self.fs.SetRoot(v0001.file_loc.clone())?;
+                    if(result != FSResult::OK)
+                    {
+                        return Err(error!("Header files location '{}' could not be used as file system root! (result {})", v0001.file_loc, result as u8));
+                    }
+                }
+            },
+        }
+
+        if(self.stack_position < 0x100)
+        {
+            self.stack_position = 0x100;
+            self.stack_pointer  = 0x100;
+        }
+
+        if(self.stack_size < 512)
+        {
+            self.stack_size = 512;
+        }
+        
+        Ok(())
+
+    }
+
+    /// argc, argv, envc, envp [dwords], then both 0 terminated pointer lists, then the strings
+    /// -> has to come after load_executable(), the whole reserved range has to be free of the image and the stack
+    pub fn set_args(&mut self, args: &[String], env: &[String]) -> Result<(), Error>
+    {
+
+        let reserved = ARGS_BLOCK_ADR..ARGS_BLOCK_ADR + ARGS_BLOCK_SIZE;
+        if(self.image_end > reserved.start)
+        {
+            return Err(error!("The executable reaches up to {:#x}, arguments need {:#x}..{:#x} to be free!", self.image_end, reserved.start, reserved.end));
+        }
+        let stack_end = self.stack_position.saturating_add(self.stack_size);
+        if(self.stack_position < reserved.end && stack_end > reserved.start)
+        {
+            return Err(error!("The stack [{:#x}..{:#x}] overlaps {:#x}..{:#x}, where the arguments go!", self.stack_position, stack_end, reserved.start, reserved.end));
+        }
+
+        let lists_len = 4 * (args.len() + 1 + env.len() + 1);
+        let strings_len: usize = args.iter().chain(env.iter()).map(|s| s.len() + 1).sum();
+        if(16 + lists_len + strings_len > ARGS_BLOCK_SIZE as usize)
+        {
+            return Err(error!("Arguments and environment need {} bytes, only {} fit!", 16 + lists_len + strings_len, ARGS_BLOCK_SIZE));
+        }
+
+        let mut block: Vec<u8> = Vec::new();
+        let push32 = |block: &mut Vec<u8>, v: u32|
+        {
+            let v = u32_2_u8(v);
+            block.extend([v.0, v.1, v.2, v.3]);
+        };
+
+        let argv = ARGS_BLOCK_ADR + 16;
+        let envp = argv + 4 * (args.len() as u32 + 1);
+        push32(&mut block, args.len() as u32);
+        push32(&mut block, argv);
+        push32(&mut block, env.len() as u32);
+        push32(&mut block, envp);
+
+        let mut str_pos = ARGS_BLOCK_ADR + 16 + lists_len as u32;
+        for list in [args, env]
+        {
+            for s in list
+            {
+                push32(&mut block, str_pos);
+                str_pos += s.len() as u32 + 1;
+            }
+            push32(&mut block, 0);
+        }
+        for s in args.iter().chain(env.iter())
+        {
+            block.extend(s.as_bytes());
+            block.push(0);
+        }
+
+        for (i, b) in block.iter().enumerate()
+        {
+            self.memory.set(ARGS_BLOCK_ADR + i as u32, *b);
+        }
+        self.set_reg(ARGS_BLOCK_ADR, IRRegister::RA);
+
+        Ok(())
+
+    }
+
+    /// -d, the first user of the hooks
+    pub fn enable_debug_print (&mut self) { self.on_instruction(|_, ip, ins| println!("[{:#010x}] Executing {:?}", ip, ins)); }
+
+    /// hooks for tools outside the interpreter [see hooks.rs], they run in the order they were added
+    /// -> the first argument is always the core it happened on
+    pub fn on_instruction (&mut self, f: impl FnMut(usize, u32, &IRInstruction) + 'static) { self.hooks.AddInstruction(Box::new(f)); }
+    pub fn on_memory_read (&mut self, f: impl FnMut(usize, MemoryAccess) + 'static)        { self.hooks.AddMemoryRead (Box::new(f)); }
+    pub fn on_memory_write(&mut self, f: impl FnMut(usize, MemoryAccess) + 'static)        { self.hooks.AddMemoryWrite(Box::new(f)); }
+    pub fn on_interrupt   (&mut self, f: impl FnMut(usize, InterruptID) + 'static)         { self.hooks.AddInterrupt  (Box::new(f)); }
+    pub fn on_io          (&mut self, f: impl FnMut(usize, u16, u32) + 'static)            { self.hooks.AddIO         (Box::new(f)); }
+    pub fn on_halt        (&mut self, f: impl FnMut(usize, u32) + 'static)                 { self.hooks.AddHalt       (Box::new(f)); }
+    pub fn enable_section_mode(&mut self) { self.section_mode = true; }
+    pub fn enable_deterministic(&mut self) { self.deterministic = true; }
+    pub fn set_seed(&mut self, seed: u64) { self.rng.seed(seed); }
+    pub fn attach_disk(&mut self, path: &str) -> Result<(), Error> { self.disk.Attach(path) }
+    pub fn allow_net_host(&mut self, host: &str) -> Result<(), Error> { self.net.Allow(host) }
+    pub fn set_ray_headless(&mut self, frames: u32) -> Result<(), Error> { self.ray.SetHeadless(frames); Ok(()) }
+    pub fn record_frames(&mut self, dir: &str, every: u32) -> Result<(), Error> { self.ray.RecordFrames(dir, every) }
+    pub fn listen_channel (&mut self, path: &str) -> Result<(), Error> { self.channel.Listen (path) }
+    pub fn connect_channel(&mut self, path: &str) -> Result<(), Error> { self.channel.Connect(path) }
+    pub fn restore_console(&mut self)
+    {
+        #[cfg(feature = "terminal")]
+        self.console.Restore();
+    }
+    pub fn enable_display(&mut self, adr: u32, headless: bool) -> Result<(), Error>
+    {
+        let mut display = DISPLAY::new(adr, headless)?;
+        display.Clear()?;
+        self.display = Some(display);
+        Ok(())
+    }
+    pub fn record_audio(&mut self) { self.audio.Record(); }
+    pub fn write_audio(&self, path: &str) -> Result<(), Error> { self.audio.WriteWav(path) }
+    pub fn display_text(&self) -> Option<String> { self.display_cells().map(|c| DISPLAY::Text(&c)) }
+    pub fn set_fs_root(&mut self, path: &str) -> Result<(), Error> { self.fs.SetSandbox(path) }
+    pub fn set_fs_read_only(&mut self) { self.fs.read_only = true; }
+    pub fn set_fs_memory(&mut self, src: &str) -> Result<(), Error> { self.fs.UseMemory(MEMFS::from_source(src)?); Ok(()) }
+    pub fn dump_fs(&self, path: &str) -> Result<(), Error> { self.fs.DumpMemory(path) }
+
+    pub fn set_core_count(&mut self, count: usize) { self.cores.resize_with(count.max(1), CoreState::new); }
+    pub fn set_core_slice(&mut self, slice: u32) { self.core_slice = slice.max(1); self.core_slice_left = self.core_slice; }
+    pub fn set_instruction_budget(&mut self, budget: u64) { self.instruction_budget = budget; }
+    pub fn budget_exhausted(&self) -> bool { self.budget_exhausted }
+    pub fn exit_status(&self) -> Option<u32> { self.exit_status }
+    pub fn core_count(&self) -> usize { self.cores.len() }
+    pub fn core_id   (&self) -> usize { self.core_id }
+
+
 
 
 
     fn _io_execute_instruction(&mut self, ins: u32) -> Result<(), Error>
     {
+
+        self.perf.Count(PerfCounter::IOCalls, 1);
+        self.hooks.IO(self.core_id, self.io_device, ins);
         
         if(ins >= 0xF0)
         {
@@ -1099,9 +1939,25 @@ impl VM
                     self.io_device = self.get_reg(IRRegister::RA) as u16;
                     Ok(())
                 },
+                // without raylib only the headless canvas can be drawn into
+                #[cfg(not(feature = "raylib"))]
+                0xF1 if !self.ray.is_headless() => self.device_unavailable(0xF1, "raylib"),
                 0xF1 => self._io_execute_instruction_rl( self.get_reg(IRRegister::RA) ),
+                // Exit()
+                0xF2 =>
+                {
+                    // stops every core, not just this one
+                    self.exit_status = Some(self.get_reg(IRRegister::RA));
+                    self.running = false;
+                    for core in self.cores.iter_mut()
+                    {
+                        core.running = false;
+                    }
+                    self.hooks.Halt(self.core_id, self.instruction_pointer);
+                    Ok(())
+                },
                 
-                _ => unreachable!(),
+                _ => Err(error!("Unknown io instruction {:#x}!", ins)),
 
             }
         }
@@ -1112,11 +1968,40 @@ impl VM
                 0x0000 => self._io_execute_instruction_fs(ins),
                 0x0001 => self._io_execute_instruction_ih(ins),
                 0x0002 => self._io_execute_instruction_mm(ins),
-                _ => unreachable!(),
+                0x0003 => self._io_execute_instruction_cm(ins),
+                0x0004 => self._io_execute_instruction_ck(ins),
+                0x0005 => self._io_execute_instruction_rn(ins),
+                0x0006 => self._io_execute_instruction_bd(ins),
+                0x0007 => self._io_execute_instruction_nt(ins),
+                0x0008 => self._io_execute_instruction_ch(ins),
+                #[cfg(feature = "terminal")]
+                0x0009 => self._io_execute_instruction_co(ins),
+                #[cfg(not(feature = "terminal"))]
+                0x0009 => self.device_unavailable(0x09, "terminal"),
+                0x000A => self._io_execute_instruction_au(ins),
+                0x000B => self._io_execute_instruction_pc(ins),
+                _ => Err(error!("Unknown io device {:#x}!", self.io_device)),
             }
         }
 
     }
+
+    /// 0 terminated string at adr
+    fn _read_str(&mut self, mut adr: u32) -> Result<String, Error>
+    {
+        let mut out = String::new();
+        loop
+        {
+            let c = self.memget(adr)?;
+            adr += 1;
+            if(c == 0)
+            {
+                break;
+            }
+            out.push(c as char);
+        }
+        Ok(out)
+    }
     fn _io_execute_instruction_fs(&mut self, ins: u32) -> Result<(), Error>
     {
 
@@ -1129,24 +2014,12 @@ impl VM
             },
             0x01 => // GetFiles()
             {
-                self.set_reg(self.fs.GetFiles()? as u32, IRRegister::RA);
+                self.set_reg(self.fs.GetFiles()?, IRRegister::RA);
             },
             0x02 => // CreateFile()
             {
                 
-                let mut name_ptr = self.get_reg(IRRegister::RA);
-                let mut name = String::new();
-
-                loop
-                {
-                    let c = self.memget(name_ptr)?;
-                    name_ptr += 1;
-                    if(c == 0)
-                    {
-                        break;
-                    }
-                    name.push(c as char);
-                }
+                let name = self._read_str(self.get_reg(IRRegister::RA))?;
 
                 let result = self.fs.CreateFile(name)? as u32;
                 self.set_reg(result, IRRegister::RB);
@@ -1154,26 +2027,14 @@ impl VM
             },
             0x03 => // DeleteFile()
             {
-                let index = self.get_reg(IRRegister::RA) as u8;
+                let index = self.get_reg(IRRegister::RA);
                 let result = self.fs.DeleteFile(index)? as u32;
                 self.set_reg(result, IRRegister::RB);
             },
             0x04 => // FileExists()
             {
                 
-                let mut name_ptr = self.get_reg(IRRegister::RA);
-                let mut name = String::new();
-
-                loop
-                {
-                    let c = self.memget(name_ptr)?;
-                    name_ptr += 1;
-                    if(c == 0)
-                    {
-                        break;
-                    }
-                    name.push(c as char);
-                }
+                let name = self._read_str(self.get_reg(IRRegister::RA))?;
 
                 let result = self.fs.FileExists(name)? as u32;
                 self.set_reg(result, IRRegister::RB);
@@ -1182,21 +2043,9 @@ impl VM
             0x05 => // GetSupDir()
             {
                 
-                let mut name_ptr = self.get_reg(IRRegister::RA);
-                let mut name = String::new();
-                
-                let dts_ptr = self.get_reg(IRRegister::RB);
+                let name = self._read_str(self.get_reg(IRRegister::RA))?;
 
-                loop
-                {
-                    let c = self.memget(name_ptr)?;
-                    name_ptr += 1;
-                    if(c == 0)
-                    {
-                        break;
-                    }
-                    name.push(c as char);
-                }
+                let dts_ptr = self.get_reg(IRRegister::RB);
 
                 let (result, path) = self.fs.GetSupDir(name)?;
                 self.set_reg(result as u32, IRRegister::RC);
@@ -1212,31 +2061,111 @@ impl VM
                 }
 
             }
-            0x0E => // QuickRead()
+            0x06 => // ChangeDir()
             {
                 
-                let mut name_ptr = self.get_reg(IRRegister::RA);
-                let mut name = String::new();
-                
-                let dts_ptr = self.get_reg(IRRegister::RB);
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
 
-                loop
+                let result = self.fs.ChangeDir(path)?;
+                self.set_reg(result as u32, IRRegister::RB);
+
+            },
+            0x07 => // GetCurrentDir()
+            {
+
+                let dst = self.get_reg(IRRegister::RA);
+                let path = self.fs.GetCurrentDir();
+
+                for (i, c) in path.bytes().enumerate()
                 {
-                    let c = self.memget(name_ptr)?;
-                    name_ptr += 1;
-                    if(c == 0)
-                    {
-                        break;
-                    }
-                    name.push(c as char);
+                    self.memset(dst.wrapping_add(i as u32), c)?;
                 }
+                self.memset(dst.wrapping_add(path.len() as u32), 0)?;
 
-                let (result, bytes) = self.fs.QuickRead(name)?;
-                self.set_reg(result as u32, IRRegister::RD);
+                self.set_reg(path.len() as u32, IRRegister::RB);
 
-                if let Some(bytes) = bytes
-                {
-                    self.set_reg(bytes.len() as u32, IRRegister::RC);
+            },
+            0x08 => // MakeDir()
+            {
+                
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let result = self.fs.MakeDir(path)?;
+                self.set_reg(result as u32, IRRegister::RB);
+
+            },
+            0x09 => // RemoveDir()
+            {
+                
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let result = self.fs.RemoveDir(path)?;
+                self.set_reg(result as u32, IRRegister::RB);
+
+            },
+            0x0A => // Stat()
+            {
+                
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let (result, stat) = self.fs.Stat(path)?;
+                let (t, size, modified) = stat.unwrap_or((0, 0, 0));
+                self.set_reg(t,        IRRegister::RA);
+                self.set_reg(size,     IRRegister::RB);
+                self.set_reg(modified, IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x0B => // ListDir()
+            {
+                
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let dst = self.get_reg(IRRegister::RB);
+                let max = self.get_reg(IRRegister::RC);
+
+                let (result, entries) = self.fs.ListDir(path)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+                // [type byte, name, 0] per entry, a 0 type ends the list; whatever doesnt fit gets left out
+                let mut pos: u32 = 0;
+                let mut count: u32 = 0;
+
+                for (t, name) in entries.unwrap_or_default()
+                {
+                    if(pos as u64 + name.len() as u64 + 3 > max as u64) { break; }
+                    self.memset(dst.wrapping_add(pos), t as u8)?;
+                    pos += 1;
+                    for c in name.bytes()
+                    {
+                        self.memset(dst.wrapping_add(pos), c)?;
+                        pos += 1;
+                    }
+                    self.memset(dst.wrapping_add(pos), 0)?;
+                    pos += 1;
+                    count += 1;
+                }
+                if(max > 0)
+                {
+                    self.memset(dst.wrapping_add(pos), 0)?;
+                }
+
+                self.set_reg(count, IRRegister::RC);
+
+            },
+            0x0E => // QuickRead()
+            {
+                
+                let name = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let dts_ptr = self.get_reg(IRRegister::RB);
+
+                let (result, bytes) = self.fs.QuickRead(name)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+                if let Some(bytes) = bytes
+                {
+                    self.set_reg(bytes.len() as u32, IRRegister::RC);
                     let mut ptr = dts_ptr;
                     for b in bytes
                     {
@@ -1249,19 +2178,7 @@ impl VM
             0x0F => // SetRoot()
             {
                 
-                let mut path_ptr = self.get_reg(IRRegister::RA);
-                let mut path = String::new();
-
-                loop
-                {
-                    let c = self.memget(path_ptr)?;
-                    path_ptr += 1;
-                    if(c == 0)
-                    {
-                        break;
-                    }
-                    path.push(c as char);
-                }
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
 
                 let result = self.fs.SetRoot(path)?;
                 self.set_reg(result as u32, IRRegister::RB);
@@ -1273,7 +2190,7 @@ impl VM
             0x10 => // GetFileName()
             {
 
-                let index = self.get_reg(IRRegister::RA) as u8;
+                let index = self.get_reg(IRRegister::RA);
                 let ptr   = self.get_reg(IRRegister::RB);
 
                 let (result, name) = self.fs.GetFileName(index)?;
@@ -1290,255 +2207,870 @@ impl VM
                         i += 1;
                     }
 
-                    // i == length of the string
-                    self.set_reg(i, IRRegister::RC);
+                    // i == length of the string
+                    self.set_reg(i, IRRegister::RC);
+
+                }
+
+            },
+            0x11 => // SetFileName()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+
+                let name = self._read_str(self.get_reg(IRRegister::RB))?;
+
+                let result = self.fs.SetFileName(index, name)?;
+                self.set_reg(result as u32, IRRegister::RC);
+
+            },
+            0x12 => // GetFileLength()
+            {
+                
+                let index = self.get_reg(IRRegister::RA);
+
+                let (result, length) = self.fs.GetFileLength(index)?;
+                self.set_reg(result as u32, IRRegister::RC);
+
+                if let Some(length) = length
+                {
+                    self.set_reg(length, IRRegister::RB);
+                }
+
+            },
+            0x13 => // SetFileLength()
+            {
+                
+                let index  = self.get_reg(IRRegister::RA);
+                let length = self.get_reg(IRRegister::RB);
+
+                let result = self.fs.SetFileLength(index, length)?;
+                self.set_reg(result as u32, IRRegister::RC);
+
+            },
+
+
+
+            0x14 => // GetEntryType()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+
+                let (result, t) = self.fs.GetEntryType(index)?;
+                self.set_reg(t.unwrap_or(0), IRRegister::RB);
+                self.set_reg(result as u32, IRRegister::RC);
+
+            },
+
+
+
+            0x20 => // ReadFile()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+                let ptr   = self.get_reg(IRRegister::RB);
+
+                let (result, buffer) = self.fs.ReadFile(index)?;
+                self.set_reg(result as u32, IRRegister::RC);
+
+                let mut i: u32 = 0;
+
+                if let Some(buffer) = buffer
+                {
+
+                    for b in buffer
+                    {
+                        self.memset(i + ptr, b)?;
+                        i += 1;
+                    }
+
+                }
+
+                self.set_reg(i, IRRegister::RD);
+
+            },
+            0x21 => // ReadFileAt()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+                let ptr   = self.get_reg(IRRegister::RB);
+                let pos   = self.get_reg(IRRegister::RC);
+
+                let (result, val) = self.fs.ReadFileAt(index, pos)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+                if let Some(val) = val
+                {
+                    self.memset(ptr, val)?;
+                }
+
+            }
+            0x22 => // WrtieFile()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+                let len   = self.get_reg(IRRegister::RB);
+                let ptr   = self.get_reg(IRRegister::RC);
+
+                let mut buffer: Vec<u8> = Vec::new();
+                
+                for i in 0..len
+                {
+                    buffer.push(self.memget(i + ptr)?);
+                }
+
+                let result = self.fs.WriteFile(index, buffer)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x23 => // WrtieFileAt()
+            {
+
+                let index = self.get_reg(IRRegister::RA);
+                let ptr   = self.get_reg(IRRegister::RB);
+                let pos   = self.get_reg(IRRegister::RC);
+
+                let val = self.memget(ptr)?;
+
+                let result = self.fs.WriteFileAt(index, pos, val)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+
+
+
+            0x30 => // Open()
+            {
+                
+                let path = self._read_str(self.get_reg(IRRegister::RA))?;
+
+                let mode = self.get_reg(IRRegister::RB);
+
+                let (result, fd) = self.fs.Open(path, mode)?;
+                self.set_reg(fd.unwrap_or(0), IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x31 => // Close()
+            {
+                let fd = self.get_reg(IRRegister::RA);
+                let result = self.fs.Close(fd)?;
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x32 => // Read()
+            {
+
+                let fd  = self.get_reg(IRRegister::RA);
+                let ptr = self.get_reg(IRRegister::RB);
+                let n   = self.get_reg(IRRegister::RC);
+
+                let (result, buffer) = self.fs.Read(fd, n)?;
+                self.set_reg(result as u32, IRRegister::RD);
+
+                let mut i: u32 = 0;
+
+                if let Some(buffer) = buffer
+                {
+                    for b in buffer
+                    {
+                        self.memset(ptr.wrapping_add(i), b)?;
+                        i += 1;
+                    }
+                }
+
+                self.set_reg(i, IRRegister::RC);
+
+            },
+            0x33 => // Write()
+            {
+
+                let fd  = self.get_reg(IRRegister::RA);
+                let ptr = self.get_reg(IRRegister::RB);
+                let n   = self.get_reg(IRRegister::RC);
+
+                let mut buffer: Vec<u8> = Vec::new();
+
+                for i in 0..n
+                {
+                    buffer.push(self.memget(ptr.wrapping_add(i))?);
+                }
+
+                let (result, written) = self.fs.Write(fd, buffer)?;
+                self.set_reg(written.unwrap_or(0), IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x34 => // Seek()
+            {
+
+                let fd     = self.get_reg(IRRegister::RA);
+                let offset = self.get_reg(IRRegister::RB) as i32;
+                let whence = self.get_reg(IRRegister::RC);
+
+                let (result, pos) = self.fs.Seek(fd, offset, whence)?;
+                if let Some(pos) = pos
+                {
+                    self.set_reg(pos, IRRegister::RB);
+                }
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x35 => // Tell()
+            {
+
+                let fd = self.get_reg(IRRegister::RA);
+
+                let (result, pos) = self.fs.Tell(fd)?;
+                self.set_reg(pos.unwrap_or(0), IRRegister::RB);
+                self.set_reg(result as u32, IRRegister::RD);
+
+            },
+            0x36 => // Truncate()
+            {
+                let fd     = self.get_reg(IRRegister::RA);
+                let length = self.get_reg(IRRegister::RB);
+                let result = self.fs.Truncate(fd, length)?;
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+
+
+
+            _ =>
+            {
+                return Err(error!("FileSystem[TM]: {:#x} is not a fs function!", ins));
+            }
+
+        }
+    
+        Ok(())
+
+    }
+    fn _io_execute_instruction_ih(&mut self, ins: u32) -> Result<(), Error>
+    {
+
+        match ins
+        {
+
+            0x00 => // GetInterruptID()
+            {
+                let id: InterruptID = 
+                    if let Some(int) = &self.interrupt
+                    {
+                        int.id
+                    }
+                    else 
+                    {
+                        InterruptID::None
+                    };
+                self.set_reg(id as u8 as u32, IRRegister::RA);
+            },
+            0x01 => // SetUserMode()
+            {
+                self.user_mode = true;
+            },
+            0x02 => // SetInterruptHandlerLocation()
+            {
+                let loc = self.get_reg(IRRegister::RA);
+                self.interrupt_location = loc;
+            },
+            0x03 => // ResolveInterrupt()
+            {
+                self.resolve_interrupt()?;
+            },
+            0x04 => // RemoveInterrupt()
+            {
+
+                let i = self.instruction_pointer;
+                let s = self.      stack_pointer;
+                let (sp, ss) = (self.stack_position, self.stack_size);
+
+                // the kernel stack is only lent out for a syscall, leaving it goes back to the stack of the caller
+                let kernel_stack = self.handling_interrupt && matches!(&self.interrupt, Some(int) if int.kernel_stack);
+
+                self.resolve_interrupt()?;
+
+                self.instruction_pointer = i;
+                if(!kernel_stack)
+                {
+                    self.      stack_pointer = s;
+                    self.stack_position = sp;
+                    self.stack_size     = ss;
+                }
+                self.user_mode = false;
+                self. sub_mode = false;
+
+            },
+            0x05 => // SetUserMode()
+            {
+                self.sub_mode = true;
+            },     
+            0x06 => // ResolveInterruptNoRSP()
+            {
+                let rsp = self.stack_pointer;
+                let (sp, ss) = (self.stack_position, self.stack_size);
+                self.resolve_interrupt()?;
+                self.stack_pointer = rsp;
+                self.stack_position = sp;
+                self.stack_size     = ss;
+            },       
+            0x07 => // SetTimer()
+            {
+                self.timer_period = self.get_reg(IRRegister::RA);
+                self.timer_next = Instant::now() + Duration::from_millis(self.timer_period as u64);
+            },
+            0x08 => // SetKeyboardInterrupt()
+            {
+                self.keyboard_interrupt = self.get_reg(IRRegister::RA) != 0;
+            },
+            0x0A => // SetChannelInterrupt()
+            {
+                self.channel_interrupt = self.get_reg(IRRegister::RA) != 0;
+            },
+            0x09 => // SetKernelStack()
+            {
+                self.kernel_stack_position = self.get_reg(IRRegister::RA);
+                self.kernel_stack_size     = self.get_reg(IRRegister::RB);
+            },
+            _ => unreachable!("{ins}"),
+
+        }
+
+        Ok(())
+
+    }
+    fn _io_execute_instruction_mm(&mut self, ins: u32) -> Result<(), Error>
+    {
+        
+        match ins
+        {
+
+            0x00 => // SuspendMapping()
+            {
+                self.memory_mapping_suspended = true;
+            },
+            0x01 => // ResumeMapping()
+            {
+                self.memory_mapping_suspended = false;
+            },
+            0x02 => // SetMap()
+            {
+
+                let adr = self.get_reg(IRRegister::RA);
+                let len = self.get_reg(IRRegister::RB);
+                let dst = self.get_reg(IRRegister::RC);
+
+                let ID = self.memory_maps.len() as u32 + 1;
+
+                self.memory_maps.push(MemoryMap::from(ID, adr, len, dst));
+
+                self.set_reg(ID, IRRegister::RD);
+
+            },
+            0x03 => // RmvMap()
+            {
+
+                let ID = self.get_reg(IRRegister::RA);
+
+                for (i,m) in self.memory_maps.iter().enumerate()
+                {
+                    if(m.id == ID)
+                    {
+                        self.memory_maps.remove(i);
+                        break;
+                    }
+                };
+
+            },
+            
+            _ => unreachable!(),
+
+        }
+
+        Ok(())
+
+    }
+    fn _io_execute_instruction_cm(&mut self, ins: u32) -> Result<(), Error>
+    {
+        
+        match ins
+        {
+
+            0x00 => // GetCoreID()
+            {
+                self.set_reg(self.core_id as u32, IRRegister::RA);
+            },
+            0x01 => // GetCoreCount()
+            {
+                self.set_reg(self.cores.len() as u32, IRRegister::RA);
+            },
+            0x02 => // StartCore()
+            {
+
+                let id = self.get_reg(IRRegister::RA) as usize;
+
+                if(id >= self.cores.len() || id == self.core_id || self.cores[id].running)
+                {
+                    self.set_reg(0, IRRegister::RA);
+                    return Ok(());
+                }
+
+                let mut core = CoreState::new();
+                core.instruction_pointer = self.get_reg(IRRegister::RB);
+                core.stack_position      = self.get_reg(IRRegister::RC);
+                core.stack_pointer       = self.get_reg(IRRegister::RC);
+                core.stack_size          = self.get_reg(IRRegister::RD);
+                core.running = true;
+                self.cores[id] = core;
+
+                self.set_reg(1, IRRegister::RA);
+
+            },
+            0x03 => // IsCoreRunning()
+            {
+                let id = self.get_reg(IRRegister::RA) as usize;
+                let running = if(id == self.core_id) { self.running } else { id < self.cores.len() && self.cores[id].running };
+                self.set_reg(running as u32, IRRegister::RA);
+            },
+            
+            _ => return Err(error!("Unknown op {:#x} for the core device!", ins)),
+
+        }
+
+        Ok(())
+
+    }
+    /// ns since the vm started, virtual when deterministic
+    fn monotonic_ns(&self) -> u64
+    {
+        if(self.deterministic)
+        {
+            self.instruction_count * VIRTUAL_NS_PER_INSTRUCTION + self.virtual_sleep_ns
+        }
+        else
+        {
+            self.start_time.elapsed().as_nanos() as u64
+        }
+    }
+    fn _io_execute_instruction_ck(&mut self, ins: u32) -> Result<(), Error>
+    {
+        
+        match ins
+        {
+
+            0x00 => // GetMonotonic()
+            {
+                let ns = self.monotonic_ns();
+                self.set_reg( ns        as u32, IRRegister::RA);
+                self.set_reg((ns >> 32) as u32, IRRegister::RB);
+            },
+            0x01 => // GetUnixTime()
+            {
+                let secs = if(self.deterministic)
+                {
+                    self.monotonic_ns() / 1_000_000_000
+                }
+                else
+                {
+                    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+                };
+                self.set_reg(secs as u32, IRRegister::RA);
+            },
+            0x02 => // Sleep()
+            {
+                let ms = self.get_reg(IRRegister::RA) as u64;
+                if(self.deterministic)
+                {
+                    self.virtual_sleep_ns += ms * 1_000_000;
+                }
+                else
+                {
+                    std::thread::sleep(Duration::from_millis(ms));
+                }
+            },
+            
+            _ => return Err(error!("Unknown op {:#x} for the clock device!", ins)),
+
+        }
+
+        Ok(())
+
+    }
+    fn _io_execute_instruction_rn(&mut self, ins: u32) -> Result<(), Error>
+    {
+        
+        match ins
+        {
+
+            0x00 => // GetRandom()
+            {
+                let v = self.rng.next_u32();
+                self.set_reg(v, IRRegister::RA);
+            },
+            0x01 => // FillRandom()
+            {
+                let adr = self.get_reg(IRRegister::RA);
+                let len = self.get_reg(IRRegister::RB).min(RNG_MAX_FILL);
+                for i in 0..len
+                {
+                    let b = self.rng.next_u32() as u8;
+                    self.memset(adr.wrapping_add(i), b)?;
+                }
+                self.set_reg(len, IRRegister::RB);
+            },
+            0x02 => // SetSeed()
+            {
+                let seed = (self.get_reg(IRRegister::RB) as u64) << 32 | self.get_reg(IRRegister::RA) as u64;
+                self.rng.seed(seed);
+            },
+            
+            _ => return Err(error!("Unknown op {:#x} for the random device!", ins)),
+
+        }
 
-                }
+        Ok(())
+
+    }
+    fn _io_execute_instruction_bd(&mut self, ins: u32) -> Result<(), Error>
+    {
+        
+        match ins
+        {
 
+            0x00 => // GetSectorCount()
+            {
+                self.set_reg(self.disk.GetSectorCount(), IRRegister::RA);
             },
-            0x11 => // SetFileName()
+            0x01 => // ReadSector()
             {
 
-                let index = self.get_reg(IRRegister::RA) as u8;
+                let sector = self.get_reg(IRRegister::RA);
+                let dst    = self.get_reg(IRRegister::RB);
 
-                let mut name_ptr = self.get_reg(IRRegister::RB);
-                let mut name = String::new();
+                let (result, bytes) = self.disk.ReadSector(sector);
+                self.set_reg(result as u32, IRRegister::RD);
 
-                loop
+                if let Some(bytes) = bytes
                 {
-                    let c = self.memget(name_ptr)?;
-                    name_ptr += 1;
-                    if(c == 0)
+                    for (i, b) in bytes.into_iter().enumerate()
                     {
-                        break;
+                        self.memset(dst.wrapping_add(i as u32), b)?;
                     }
-                    name.push(c as char);
                 }
 
-                let result = self.fs.SetFileName(index, name)?;
-                self.set_reg(result as u32, IRRegister::RC);
-
             },
-            0x12 => // GetFileLength()
+            0x02 => // WriteSector()
             {
-                
-                let index = self.get_reg(IRRegister::RA) as u8;
 
-                let (result, length) = self.fs.GetFileLength(index)?;
-                self.set_reg(result as u32, IRRegister::RC);
+                let sector = self.get_reg(IRRegister::RA);
+                let src    = self.get_reg(IRRegister::RB);
 
-                if let Some(length) = length
+                let mut buffer = Vec::with_capacity(DISK_SECTOR_SIZE as usize);
+                for i in 0..DISK_SECTOR_SIZE
                 {
-                    self.set_reg(length, IRRegister::RB);
+                    buffer.push(self.memget(src.wrapping_add(i))?);
                 }
 
+                let result = self.disk.WriteSector(sector, buffer);
+                self.set_reg(result as u32, IRRegister::RD);
+
             },
-            0x13 => // SetFileLength()
+            0x03 => // Flush()
             {
-                
-                let index  = self.get_reg(IRRegister::RA) as u8;
-                let length = self.get_reg(IRRegister::RB);
+                let result = self.disk.Flush();
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x04 => // GetSectorSize()
+            {
+                self.set_reg(DISK_SECTOR_SIZE, IRRegister::RA);
+            },
+            
+            _ => return Err(error!("Unknown op {:#x} for the block device!", ins)),
 
-                let result = self.fs.SetFileLength(index, length)?;
-                self.set_reg(result as u32, IRRegister::RC);
+        }
+
+        Ok(())
+
+    }
+
+    fn _io_execute_instruction_nt(&mut self, ins: u32) -> Result<(), Error>
+    {
+
+        match ins
+        {
 
+            0x00 => // Connect()
+            {
+                let addr = self._read_str(self.get_reg(IRRegister::RA))?;
+                let (result, handle) = self.net.Connect(&addr);
+                self.set_reg(handle.unwrap_or(0), IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x01 => // Listen()
+            {
+                let addr = self._read_str(self.get_reg(IRRegister::RA))?;
+                let (result, handle) = self.net.Listen(&addr);
+                self.set_reg(handle.unwrap_or(0), IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x02 => // Accept()
+            {
+                let (result, handle) = self.net.Accept(self.get_reg(IRRegister::RA));
+                self.set_reg(handle.unwrap_or(0), IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
             },
+            0x03 => // Send()
+            {
 
+                let handle = self.get_reg(IRRegister::RA);
+                let ptr    = self.get_reg(IRRegister::RB);
+                let n      = self.get_reg(IRRegister::RC).min(NET_MAX_TRANSFER);
+
+                let mut buffer: Vec<u8> = Vec::with_capacity(n as usize);
+                for i in 0..n
+                {
+                    buffer.push(self.memget(ptr.wrapping_add(i))?);
+                }
 
+                let (result, sent) = self.net.Send(handle, buffer);
+                self.set_reg(sent, IRRegister::RC);
+                self.set_reg(result as u32, IRRegister::RD);
 
-            0x20 => // ReadFile()
+            },
+            0x04 => // Receive()
             {
 
-                let index = self.get_reg(IRRegister::RA) as u8;
-                let ptr   = self.get_reg(IRRegister::RB);
+                let handle = self.get_reg(IRRegister::RA);
+                let ptr    = self.get_reg(IRRegister::RB);
+                let n      = self.get_reg(IRRegister::RC);
 
-                let (result, buffer) = self.fs.ReadFile(index)?;
-                self.set_reg(result as u32, IRRegister::RC);
+                let (result, buffer) = self.net.Receive(handle, n);
+                self.set_reg(result as u32, IRRegister::RD);
 
                 let mut i: u32 = 0;
-
                 if let Some(buffer) = buffer
                 {
-
                     for b in buffer
                     {
-                        self.memset(i + ptr, b)?;
+                        self.memset(ptr.wrapping_add(i), b)?;
                         i += 1;
                     }
-
                 }
-
-                self.set_reg(i, IRRegister::RD);
+                self.set_reg(i, IRRegister::RC);
 
             },
-            0x21 => // ReadFileAt()
+            0x05 => // Close()
+            {
+                let result = self.net.Close(self.get_reg(IRRegister::RA));
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x06 => // GetPort()
             {
+                let (result, port) = self.net.GetPort(self.get_reg(IRRegister::RA));
+                self.set_reg(port, IRRegister::RB);
+                self.set_reg(result as u32, IRRegister::RD);
+            },
 
-                let index = self.get_reg(IRRegister::RA) as u8;
-                let ptr   = self.get_reg(IRRegister::RB);
-                let pos   = self.get_reg(IRRegister::RC);
+            _ => return Err(error!("Unknown op {:#x} for the net device!", ins)),
 
-                let (result, val) = self.fs.ReadFileAt(index, pos)?;
-                self.set_reg(result as u32, IRRegister::RD);
+        }
 
-                if let Some(val) = val
-                {
-                    self.memset(ptr, val)?;
-                }
+        Ok(())
 
-            }
-            0x22 => // WrtieFile()
+    }
+
+    fn _io_execute_instruction_ch(&mut self, ins: u32) -> Result<(), Error>
+    {
+
+        match ins
+        {
+
+            0x00 => // Send()
             {
 
-                let index = self.get_reg(IRRegister::RA) as u8;
-                let len   = self.get_reg(IRRegister::RB);
-                let ptr   = self.get_reg(IRRegister::RC);
+                let ptr = self.get_reg(IRRegister::RA);
+                let n   = self.get_reg(IRRegister::RB);
 
-                let mut buffer: Vec<u8> = Vec::new();
-                
-                for i in 0..len
+                if(n > CHANNEL_MAX_MESSAGE)
                 {
-                    buffer.push(self.memget(i + ptr)?);
+                    self.set_reg(ChannelResult::TooLong as u32, IRRegister::RD);
+                    return Ok(());
                 }
 
-                let result = self.fs.WriteFile(index, buffer)?;
+                let mut message: Vec<u8> = Vec::with_capacity(n as usize);
+                for i in 0..n
+                {
+                    message.push(self.memget(ptr.wrapping_add(i))?);
+                }
+
+                let result = self.channel.Send(message);
                 self.set_reg(result as u32, IRRegister::RD);
 
             },
-            0x23 => // WrtieFileAt()
+            0x01 => // Receive()
             {
 
-                let index = self.get_reg(IRRegister::RA) as u8;
-                let ptr   = self.get_reg(IRRegister::RB);
-                let pos   = self.get_reg(IRRegister::RC);
-
-                let val = self.memget(ptr)?;
+                let ptr = self.get_reg(IRRegister::RA);
+                let max = self.get_reg(IRRegister::RB);
 
-                let result = self.fs.WriteFileAt(index, pos, val)?;
+                let (result, message, len) = self.channel.Receive(max);
+                self.set_reg(len, IRRegister::RB);
                 self.set_reg(result as u32, IRRegister::RD);
 
-            },
-
-
+                if let Some(message) = message
+                {
+                    for (i, b) in message.into_iter().enumerate()
+                    {
+                        self.memset(ptr.wrapping_add(i as u32), b)?;
+                    }
+                }
 
-            _ =>
+            },
+            0x02 => // Poll()
             {
-                return Err(error!("FileSystem[TM]: {:#x} is not a fs function!", ins));
-            }
+                let (result, count, next) = self.channel.Poll();
+                self.set_reg(count, IRRegister::RA);
+                self.set_reg(next, IRRegister::RB);
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+
+            _ => return Err(error!("Unknown op {:#x} for the channel device!", ins)),
 
         }
-    
+
         Ok(())
 
     }
-    fn _io_execute_instruction_ih(&mut self, ins: u32) -> Result<(), Error>
+
+    #[cfg(feature = "terminal")]
+    fn _io_execute_instruction_co(&mut self, ins: u32) -> Result<(), Error>
     {
 
         match ins
         {
 
-            0x00 => // GetInterruptID()
+            0x00 => // Clear()
             {
-                let id: InterruptID = 
-                    if let Some(int) = &self.interrupt
-                    {
-                        int.id
-                    }
-                    else 
-                    {
-                        InterruptID::None
-                    };
-                self.set_reg(id as u8 as u32, IRRegister::RA);
+                self.console.Clear()?;
             },
-            0x01 => // SetUserMode()
+            0x01 => // ClearLine()
             {
-                self.user_mode = true;
+                self.console.ClearLine()?;
             },
-            0x02 => // SetInterruptHandlerLocation()
+            0x02 => // SetCursor()
             {
-                let loc = self.get_reg(IRRegister::RA);
-                self.interrupt_location = loc;
+                self.console.SetCursor(self.get_reg(IRRegister::RA), self.get_reg(IRRegister::RB))?;
             },
-            0x03 => // ResolveInterrupt()
+            0x03 => // ShowCursor()
             {
-                self.resolve_interrupt()?;
+                self.console.ShowCursor(self.get_reg(IRRegister::RA) != 0)?;
             },
-            0x04 => // RemoveInterrupt()
+            0x04 => // SetForeground()
             {
-
-                let i = self.instruction_pointer;
-                let s = self.      stack_pointer;
-
-                self.resolve_interrupt()?;
-
-                self.instruction_pointer = i;
-                self.      stack_pointer = s;
-                self.user_mode = false;
-                self. sub_mode = false;
-
+                self.console.SetForeground(self.get_reg(IRRegister::RA))?;
             },
-            0x05 => // SetUserMode()
+            0x05 => // SetBackground()
             {
-                self.sub_mode = true;
-            },     
-            0x06 => // ResolveInterruptNoRSP()
+                self.console.SetBackground(self.get_reg(IRRegister::RA))?;
+            },
+            0x06 => // ResetColors()
             {
-                let rsp = self.stack_pointer;
-                self.resolve_interrupt()?;
-                self.stack_pointer = rsp;
-            },       
-            _ => unreachable!("{ins}"),
+                self.console.ResetColors()?;
+            },
+            0x07 => // GetSize()
+            {
+                let (cols, rows) = self.console.GetSize();
+                self.set_reg(cols, IRRegister::RA);
+                self.set_reg(rows, IRRegister::RB);
+            },
+            0x08 => // SetAlternateScreen()
+            {
+                self.console.SetAlternateScreen(self.get_reg(IRRegister::RA) != 0)?;
+            },
+
+            _ => return Err(error!("Unknown op {:#x} for the console device!", ins)),
 
         }
 
         Ok(())
 
     }
-    fn _io_execute_instruction_mm(&mut self, ins: u32) -> Result<(), Error>
+    fn _io_execute_instruction_au(&mut self, ins: u32) -> Result<(), Error>
     {
-        
+
         match ins
         {
 
-            0x00 => // SuspendMapping()
-            {
-                self.memory_mapping_suspended = true;
-            },
-            0x01 => // ResumeMapping()
+            0x00 => // SetFormat()
             {
-                self.memory_mapping_suspended = false;
+                let result = self.audio.SetFormat(self.get_reg(IRRegister::RA), self.get_reg(IRRegister::RB));
+                self.set_reg(result as u32, IRRegister::RD);
             },
-            0x02 => // SetMap()
+            0x01 => // Queue()
             {
 
-                let adr = self.get_reg(IRRegister::RA);
-                let len = self.get_reg(IRRegister::RB);
-                let dst = self.get_reg(IRRegister::RC);
+                let ptr   = self.get_reg(IRRegister::RA);
+                let count = self.get_reg(IRRegister::RB);
 
-                let ID = self.memory_maps.len() as u32 + 1;
+                // before reading anything, rb can ask for far more than will ever be kept
+                if(!self.audio.Fits(count as u64))
+                {
+                    self.set_reg(AudioResult::TooLong as u32, IRRegister::RD);
+                    return Ok(());
+                }
 
-                self.memory_maps.push(MemoryMap::from(ID, adr, len, dst));
+                let n = count * self.audio.Bytes();
+                let mut samples: Vec<u8> = Vec::with_capacity(n as usize);
+                for i in 0..n
+                {
+                    samples.push(self.memget(ptr.wrapping_add(i))?);
+                }
 
-                self.set_reg(ID, IRRegister::RD);
+                let result = self.audio.Queue(samples);
+                self.set_reg(result as u32, IRRegister::RD);
 
             },
-            0x03 => // RmvMap()
+            0x02 => // Tone()
+            {
+                let result = self.audio.Tone(self.get_reg(IRRegister::RA), self.get_reg(IRRegister::RB), self.get_reg(IRRegister::RC));
+                self.set_reg(result as u32, IRRegister::RD);
+            },
+            0x03 => // SetVolume()
             {
+                self.audio.SetVolume(self.get_reg(IRRegister::RA));
+            },
+            0x04 => // GetQueued()
+            {
+                self.set_reg(self.audio.Queued(), IRRegister::RA);
+            },
+            _ => return Err(error!("Unknown op {:#x} for the audio device!", ins)),
 
-                let ID = self.get_reg(IRRegister::RA);
+        }
 
-                for (i,m) in self.memory_maps.iter().enumerate()
+        Ok(())
+
+    }
+    fn _io_execute_instruction_pc(&mut self, ins: u32) -> Result<(), Error>
+    {
+
+        match ins
+        {
+
+            0x00 => // Read()
+            {
+                let (value, result) = match self.perf.Read(self.get_reg(IRRegister::RA))
                 {
-                    if(m.id == ID)
-                    {
-                        self.memory_maps.remove(i);
-                        break;
-                    }
+                    Some(v) => (v, 0),
+                    None => (0, 1),
                 };
-
+                self.set_reg(value as u32, IRRegister::RA);
+                self.set_reg((value >> 32) as u32, IRRegister::RB);
+                self.set_reg(result, IRRegister::RD);
             },
-            
-            _ => unreachable!(),
+            0x01 => // Reset()
+            {
+                self.perf.Reset();
+            },
+            _ => return Err(error!("Unknown op {:#x} for the perf counter device!", ins)),
 
         }
 
@@ -1632,6 +3164,12 @@ impl VM
                 self.ray.DrawText(x, y, s, color, text)?;
                 
             },
+            0x07 => // TakeScreenshot()
+            {
+                let ptr = self.stack_pop32()?;
+                let name = self._read_str(ptr)?;
+                self.ray.TakeScreenshot(name)?;
+            },
 
             0xED => // SetTargetFPS()
             {
@@ -1689,6 +3227,20 @@ impl VM
     }
     
 
+    /// the device was left out when erebos_vm was built, the guest gets interrupt id 6 with the device in rb
+    /// -> without an interrupt handler [location 0] it would just jump to 0, so the vm stops with an error instead
+    #[cfg(not(all(feature = "raylib", feature = "terminal")))]
+    fn device_unavailable(&mut self, device: u32, feature: &str) -> Result<(), Error>
+    {
+        if(self.interrupt_location == 0)
+        {
+            return Err(error!("IO device {:#x} is unavailable, erebos_vm was built without the '{}' feature!", device, feature));
+        }
+        self.send_interrupt(InterruptID::DeviceUnavailable)?;
+        self.set_reg(device, IRRegister::RB);
+        Ok(())
+    }
+
     fn validate_kernel_mode(&mut self, sub_mode_valid: bool) -> Result<bool, Error>
     {
         if(self.user_mode || (self.sub_mode && sub_mode_valid))
@@ -1710,6 +3262,9 @@ impl VM
             return Err(error!("Cannot send empty Interrupt ID!"));
          }
 
+         self.perf.Count(PerfCounter::Interrupts, 1);
+         self.hooks.Interrupt(self.core_id, id);
+
          let state = self.get_interrupt_state();
 
          self.user_mode = false;
@@ -1718,6 +3273,7 @@ impl VM
          self.instruction_pointer = self.interrupt_location;
 
          self.interrupt = Some(Interrupt::new(id, state));
+         self.handling_interrupt = true;
 
          Ok(())
 
@@ -1728,6 +3284,7 @@ impl VM
         if let Some(int) = &self.interrupt
         {
             self.restore_interrupt_state(int.state.clone());
+            self.handling_interrupt = false;
             Ok(())
         }
         else
@@ -1737,6 +3294,35 @@ impl VM
 
     }
 
+    /// like resolve_interrupt but keeps the registers for return values,
+    /// the stack only goes back if syscall switched away from it
+    fn sysret(&mut self) -> Result<(), Error>
+    {
+
+        let (state, kernel_stack) = match &self.interrupt
+        {
+            Some(int) => (int.state.clone(), int.kernel_stack),
+            None => return Err(error!("Cannot sysret; No interrupt present!")),
+        };
+
+        self.flags = state.flags;
+        self.instruction_pointer = state.instruction_pointer;
+        self.user_mode = state.user_mode;
+        self. sub_mode = state. sub_mode;
+
+        if(kernel_stack)
+        {
+            self.stack_pointer  = state.stack_pointer;
+            self.stack_position = state.stack_position;
+            self.stack_size     = state.stack_size;
+        }
+
+        self.handling_interrupt = false;
+
+        Ok(())
+
+    }
+
     fn get_interrupt_state(&self) -> InterruptState
     {
         InterruptState
@@ -1745,6 +3331,8 @@ impl VM
             flags: self.flags,
             instruction_pointer: self.instruction_pointer,
             stack_pointer: self.stack_pointer,
+            stack_position: self.stack_position,
+            stack_size: self.stack_size,
             user_mode: self.user_mode,
              sub_mode: self. sub_mode,
         }
@@ -1755,6 +3343,8 @@ impl VM
         self.flags = state.flags;
         self.instruction_pointer = state.instruction_pointer;
         self.stack_pointer = state.stack_pointer;
+        self.stack_position = state.stack_position;
+        self.stack_size = state.stack_size;
         self.user_mode = state.user_mode;
         self. sub_mode = state. sub_mode;
     }